@@ -0,0 +1,278 @@
+use crate::{
+    session_diff::SessionInfoDiff,
+    session_info::{Driver, DriverInfo},
+    IbtReader, SessionSamples,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StintDriver {
+    pub user_id: i32,
+    pub user_name: String,
+    pub team_id: i32,
+    pub team_name: String,
+}
+
+// One driver's time in the car.
+pub struct DriverStint {
+    // Unknown when the swap was only seen in the telemetry and no session
+    // info update named the new driver.
+    pub driver: Option<StintDriver>,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub start_lap: i32,
+    pub end_lap: i32,
+    pub incidents: i32,
+    // litres, refuelling isn't taken off
+    pub fuel_used: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StintTick {
+    pub session_time: f64,
+    pub lap: i32,
+    pub on_pit_road: bool,
+    // `PlayerCarDriverIncidentCount`, which starts from 0 for every driver
+    pub driver_incidents: i32,
+    pub fuel_level: f32,
+}
+
+// Splits a team session into driver stints. A swap is detected either from
+// a session info update naming another driver for the car or from the
+// driver incident count being reset around a pit stop.
+pub struct DriverStints {
+    pub stints: Vec<DriverStint>,
+    last: Option<StintTick>,
+    last_pit_lap: Option<i32>,
+}
+
+impl From<&Driver> for StintDriver {
+    fn from(driver: &Driver) -> StintDriver {
+        StintDriver {
+            user_id: driver.user_id,
+            user_name: driver.user_name.clone(),
+            team_id: driver.team_id,
+            team_name: driver.team_name.clone(),
+        }
+    }
+}
+
+impl DriverStint {
+    pub fn laps(&self) -> i32 {
+        self.end_lap - self.start_lap
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+}
+
+impl DriverStints {
+    pub fn new(driver: Option<StintDriver>) -> DriverStints {
+        DriverStints {
+            stints: vec![DriverStint {
+                driver,
+                start_time: 0.0,
+                end_time: 0.0,
+                start_lap: 0,
+                end_lap: 0,
+                incidents: 0,
+                fuel_used: 0.0,
+            }],
+            last: None,
+            last_pit_lap: None,
+        }
+    }
+
+    // Only the samples of `session`, a new session starts with a new stint.
+    pub fn from_reader(reader: &mut IbtReader, session: &SessionSamples) -> Option<DriverStints> {
+        let session_time = reader.find_var("SessionTime".to_string())?;
+        let lap = reader.find_var("Lap".to_string())?;
+        let on_pit_road = reader.find_var("OnPitRoad".to_string())?;
+        let incidents = reader.find_var("PlayerCarDriverIncidentCount".to_string())?;
+        let fuel_level = reader.find_var("FuelLevel".to_string())?;
+
+        let mut stints = DriverStints::new(None);
+        for sample in reader.session_samples(session) {
+            stints.push(StintTick {
+                session_time: sample.get_by_header(&session_time).unwrap().float_64(),
+                lap: sample.get_by_header(&lap).unwrap().int(),
+                on_pit_road: sample.get_by_header(&on_pit_road).unwrap().bool(),
+                driver_incidents: sample.get_by_header(&incidents).unwrap().int(),
+                fuel_level: sample.get_by_header(&fuel_level).unwrap().float_32(),
+            });
+        }
+
+        // The session info is the version at the end of the file, so it names
+        // whoever drove last. With a single stint that's only certain when
+        // the session info was never updated during the recording.
+        if stints.stints.len() > 1 || reader.header.sesion_info_update == 0 {
+            stints.stints.last_mut().unwrap().driver =
+                player_driver(&reader.session_info.driver_info);
+        }
+        Some(stints)
+    }
+
+    pub fn push(&mut self, tick: StintTick) {
+        if tick.on_pit_road {
+            self.last_pit_lap = Some(tick.lap);
+        }
+
+        let last = match self.last.replace(tick) {
+            Some(last) => last,
+            None => {
+                let stint = self.stints.last_mut().unwrap();
+                stint.start_time = tick.session_time;
+                stint.end_time = tick.session_time;
+                stint.start_lap = tick.lap;
+                stint.end_lap = tick.lap;
+                stint.incidents = tick.driver_incidents;
+                return;
+            }
+        };
+
+        let pitted = self.last_pit_lap.is_some_and(|lap| tick.lap - lap <= 1);
+        let swapped = tick.driver_incidents < last.driver_incidents && pitted;
+        if swapped {
+            self.start_stint(None, tick);
+        }
+        // the new driver's count starts from 0
+        let previous_incidents = if swapped { 0 } else { last.driver_incidents };
+
+        let stint = self.stints.last_mut().unwrap();
+        stint.end_time = tick.session_time;
+        stint.end_lap = tick.lap;
+        stint.incidents += (tick.driver_incidents - previous_incidents).max(0);
+        stint.fuel_used += (last.fuel_level - tick.fuel_level).max(0.0);
+    }
+
+    // A session info update named `driver` as the one in the car.
+    pub fn driver_changed(&mut self, driver: StintDriver) {
+        let stint = self.stints.last_mut().unwrap();
+        match (&stint.driver, self.last) {
+            (Some(current), _) if current.user_id == driver.user_id => {}
+            (Some(_), Some(tick)) => self.start_stint(Some(driver), tick),
+            // the telemetry already showed the swap, now we know who it is
+            _ => stint.driver = Some(driver),
+        }
+    }
+
+    // Picks up driver swaps from the changes `LiveReader::refresh` returns.
+    pub fn apply_diff(&mut self, diff: &SessionInfoDiff, driver_info: &DriverInfo) {
+        let driver = match player_driver(driver_info) {
+            Some(driver) => driver,
+            None => return,
+        };
+        let path = format!(
+            "DriverInfo.Drivers[CarIdx={}].UserID",
+            driver_info.driver_car_idx
+        );
+        if diff.under(&path).next().is_some() {
+            self.driver_changed(driver);
+        }
+    }
+
+    fn start_stint(&mut self, driver: Option<StintDriver>, tick: StintTick) {
+        self.stints.push(DriverStint {
+            driver,
+            start_time: tick.session_time,
+            end_time: tick.session_time,
+            start_lap: tick.lap,
+            end_lap: tick.lap,
+            incidents: 0,
+            fuel_used: 0.0,
+        });
+    }
+}
+
+fn player_driver(driver_info: &DriverInfo) -> Option<StintDriver> {
+    driver_info
+        .drivers
+        .iter()
+        .find(|driver| driver.car_idx == driver_info.driver_car_idx)
+        .map(StintDriver::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_diff::Change;
+
+    #[test]
+    fn test_driver_stints() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let session = reader.sessions().remove(0);
+        let stints = DriverStints::from_reader(&mut reader, &session).unwrap();
+        assert_eq!(stints.stints.len(), 1);
+        let stint = &stints.stints[0];
+        assert_eq!(
+            stint
+                .driver
+                .as_ref()
+                .map(|driver| driver.user_name.as_str()),
+            Some("Georgi Martsenkov")
+        );
+        assert_eq!(stint.incidents, 2);
+        assert!((stint.fuel_used - 0.867).abs() < 0.01);
+
+        // the session info changed during the recording, the driver it names
+        // could have taken over at any point
+        let mut data = std::fs::read("./test/fixtures/amg.ibt").unwrap();
+        data[12..16].copy_from_slice(&3_i32.to_le_bytes());
+        let mut updated = IbtReader::try_new(Box::new(std::io::Cursor::new(data))).unwrap();
+        let stints = DriverStints::from_reader(&mut updated, &session).unwrap();
+        assert!(stints.stints[0].driver.is_none());
+
+        let tick = |session_time: f64, lap: i32, on_pit_road: bool, incidents: i32| StintTick {
+            session_time,
+            lap,
+            on_pit_road,
+            driver_incidents: incidents,
+            fuel_level: 50.0 - session_time as f32 / 100.0,
+        };
+        let mut stints = DriverStints::new(None);
+        stints.push(tick(0.0, 1, false, 0));
+        stints.push(tick(100.0, 2, false, 4));
+        // a reset without a pit stop isn't a swap
+        stints.push(tick(150.0, 2, false, 0));
+        stints.push(tick(200.0, 3, true, 1));
+        stints.push(tick(210.0, 3, true, 0));
+        stints.push(tick(300.0, 4, false, 2));
+        assert_eq!(stints.stints.len(), 2);
+        assert_eq!(stints.stints[0].incidents, 5);
+        assert_eq!(stints.stints[0].laps(), 2);
+        assert_eq!(stints.stints[1].start_time, 210.0);
+        assert_eq!(stints.stints[1].incidents, 2);
+        assert!(stints.stints[1].driver.is_none());
+
+        let driver_info = &mut reader.session_info.driver_info;
+        driver_info.drivers[0].user_id = 42;
+        driver_info.drivers[0].user_name = "Team Mate".to_string();
+        let diff = SessionInfoDiff {
+            update: 2,
+            changes: vec![Change::Changed {
+                path: format!(
+                    "DriverInfo.Drivers[CarIdx={}].UserID",
+                    driver_info.driver_car_idx
+                ),
+                old: 1.into(),
+                new: 42.into(),
+            }],
+        };
+        stints.apply_diff(&diff, driver_info);
+        assert_eq!(stints.stints.len(), 2);
+        assert_eq!(
+            stints.stints[1]
+                .driver
+                .as_ref()
+                .map(|driver| driver.user_id),
+            Some(42)
+        );
+
+        // the next swap is only announced by the session info
+        stints.push(tick(400.0, 5, true, 2));
+        driver_info.drivers[0].user_id = 7;
+        stints.apply_diff(&diff, driver_info);
+        assert_eq!(stints.stints.len(), 3);
+        assert_eq!(stints.stints[2].start_time, 400.0);
+    }
+}
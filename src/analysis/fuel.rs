@@ -0,0 +1,343 @@
+use crate::{session_info::DriverInfo, IbtReader, SessionSamples};
+
+// Ignore fuel level jumps smaller than this on pit road, they are sloshing
+// rather than actual refuelling.
+const MIN_REFUEL_LTR: f32 = 0.1;
+
+pub struct LapFuel {
+    pub lap: i32,
+    pub lap_time: f64,
+    pub used: f32,
+    pub refuelled: bool,
+}
+
+pub struct FuelTick {
+    pub session_time: f64,
+    pub lap: i32,
+    pub fuel_level: f32,
+    // litres per hour, converted from the kg/h iRacing reports
+    pub use_per_hour: f32,
+    pub laps_remaining: Option<f32>,
+}
+
+pub struct Refuel {
+    pub lap: i32,
+    pub session_time: f64,
+    pub amount: f32,
+}
+
+pub enum RaceLength {
+    Laps(u32),
+    Time(f64),
+}
+
+pub struct Stint {
+    pub laps: u32,
+    pub fuel: f32,
+}
+
+pub struct StintPlan {
+    pub laps: u32,
+    pub fuel_per_lap: f32,
+    pub total_fuel: f32,
+    pub stints: Vec<Stint>,
+}
+
+// One tick of the player car's fuel telemetry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuelSample {
+    pub session_time: f64,
+    pub lap: i32,
+    pub fuel_level: f32,
+    // kg/h, as iRacing reports it
+    pub fuel_use_per_hour: f32,
+    pub on_pit_road: bool,
+}
+
+pub struct FuelAnalysis {
+    pub tank_capacity: f32,
+    pub window: usize,
+    pub laps: Vec<LapFuel>,
+    pub ticks: Vec<FuelTick>,
+    pub refuels: Vec<Refuel>,
+    kg_per_ltr: f32,
+    est_lap_time: f32,
+    current_lap: Option<i32>,
+    // (session time, fuel level) at the start of the current lap, only
+    // known once we've seen a lap crossing
+    lap_start: Option<(f64, f32)>,
+    lap_refuelled: f32,
+    pit_min_level: Option<f32>,
+    per_lap: Option<f32>,
+}
+
+impl FuelAnalysis {
+    // `window` is the number of clean laps used for the rolling average.
+    pub fn new(driver_info: &DriverInfo, window: usize) -> FuelAnalysis {
+        FuelAnalysis {
+            tank_capacity: driver_info.driver_car_fuel_max_ltr
+                * driver_info.driver_car_max_fuel_pct,
+            window,
+            laps: vec![],
+            ticks: vec![],
+            refuels: vec![],
+            kg_per_ltr: driver_info.driver_car_fuel_kg_per_ltr,
+            est_lap_time: driver_info.driver_car_est_lap_time,
+            current_lap: None,
+            lap_start: None,
+            lap_refuelled: 0.0,
+            pit_min_level: None,
+            per_lap: None,
+        }
+    }
+
+    // Lap numbers and fuel per lap only make sense within one session.
+    pub fn from_reader(
+        reader: &mut IbtReader,
+        session: &SessionSamples,
+        window: usize,
+    ) -> Option<FuelAnalysis> {
+        let session_time = reader.find_var("SessionTime".to_string())?;
+        let lap = reader.find_var("Lap".to_string())?;
+        let fuel_level = reader.find_var("FuelLevel".to_string())?;
+        let fuel_use = reader.find_var("FuelUsePerHour".to_string())?;
+        let on_pit_road = reader.find_var("OnPitRoad".to_string())?;
+
+        let mut analysis = FuelAnalysis::new(&reader.session_info.driver_info, window);
+        for sample in reader.session_samples(session) {
+            analysis.push(FuelSample {
+                session_time: sample.get_by_header(&session_time).unwrap().float_64(),
+                lap: sample.get_by_header(&lap).unwrap().int(),
+                fuel_level: sample.get_by_header(&fuel_level).unwrap().float_32(),
+                fuel_use_per_hour: sample.get_by_header(&fuel_use).unwrap().float_32(),
+                on_pit_road: sample.get_by_header(&on_pit_road).unwrap().bool(),
+            });
+        }
+        Some(analysis)
+    }
+
+    pub fn push(&mut self, sample: FuelSample) {
+        let FuelSample {
+            session_time: time,
+            lap: lap_num,
+            fuel_level: level,
+            ..
+        } = sample;
+        let use_per_hour = if self.kg_per_ltr > 0.0 {
+            sample.fuel_use_per_hour / self.kg_per_ltr
+        } else {
+            0.0
+        };
+
+        match (sample.on_pit_road, self.pit_min_level) {
+            (true, None) => self.pit_min_level = Some(level),
+            (true, Some(min)) => self.pit_min_level = Some(min.min(level)),
+            (false, Some(min)) => {
+                if level - min > MIN_REFUEL_LTR {
+                    self.refuels.push(Refuel {
+                        lap: lap_num,
+                        session_time: time,
+                        amount: level - min,
+                    });
+                    self.lap_refuelled += level - min;
+                }
+                self.pit_min_level = None;
+            }
+            (false, None) => {}
+        }
+
+        if let Some(current) = self.current_lap.filter(|current| lap_num > *current) {
+            if let Some((start_time, start_level)) = self.lap_start {
+                self.laps.push(LapFuel {
+                    lap: current,
+                    lap_time: time - start_time,
+                    used: start_level - level + self.lap_refuelled,
+                    refuelled: self.lap_refuelled > 0.0,
+                });
+                self.per_lap = self.window_average(self.laps.len() - 1);
+            }
+            self.lap_start = Some((time, level));
+            self.lap_refuelled = 0.0;
+        }
+        self.current_lap = Some(lap_num);
+
+        let laps_remaining = match self.per_lap {
+            Some(per_lap) if per_lap > 0.0 => Some(level / per_lap),
+            _ if use_per_hour > 0.0 && self.est_lap_time > 0.0 => {
+                Some(level / (use_per_hour * self.est_lap_time / 3600.0))
+            }
+            _ => None,
+        };
+        self.ticks.push(FuelTick {
+            session_time: time,
+            lap: lap_num,
+            fuel_level: level,
+            use_per_hour,
+            laps_remaining,
+        });
+    }
+
+    // Average consumption of the last `window` clean laps, for every lap.
+    pub fn rolling_average(&self) -> Vec<Option<f32>> {
+        (0..self.laps.len())
+            .map(|index| self.window_average(index))
+            .collect()
+    }
+
+    pub fn average_per_lap(&self) -> Option<f32> {
+        let clean: Vec<f32> = self.clean_laps().map(|lap| lap.used).collect();
+        average(&clean)
+    }
+
+    pub fn average_lap_time(&self) -> Option<f64> {
+        let times: Vec<f32> = self.clean_laps().map(|lap| lap.lap_time as f32).collect();
+        average(&times).map(|time| time as f64)
+    }
+
+    pub fn stint_plan(&self, length: RaceLength) -> Option<StintPlan> {
+        let fuel_per_lap = self.average_per_lap()?;
+        let laps = match length {
+            RaceLength::Laps(laps) => laps,
+            // the lap in progress when the clock runs out still has to be finished
+            RaceLength::Time(seconds) => (seconds / self.average_lap_time()?).ceil() as u32,
+        };
+        let laps_per_tank = (self.tank_capacity / fuel_per_lap).floor() as u32;
+        if laps_per_tank == 0 {
+            return None;
+        }
+
+        let mut stints = vec![];
+        let mut remaining = laps;
+        while remaining > 0 {
+            let stint_laps = remaining.min(laps_per_tank);
+            stints.push(Stint {
+                laps: stint_laps,
+                fuel: stint_laps as f32 * fuel_per_lap,
+            });
+            remaining -= stint_laps;
+        }
+
+        Some(StintPlan {
+            laps,
+            fuel_per_lap,
+            total_fuel: laps as f32 * fuel_per_lap,
+            stints,
+        })
+    }
+
+    fn window_average(&self, index: usize) -> Option<f32> {
+        let clean: Vec<f32> = self.laps[..=index]
+            .iter()
+            .rev()
+            .filter(|lap| !lap.refuelled)
+            .take(self.window)
+            .map(|lap| lap.used)
+            .collect();
+        average(&clean)
+    }
+
+    fn clean_laps(&self) -> impl Iterator<Item = &LapFuel> {
+        self.laps.iter().filter(|lap| !lap.refuelled)
+    }
+}
+
+fn average(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f32>() / values.len() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_fuel_analysis() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let session = reader.sessions().remove(0);
+        let mut analysis = FuelAnalysis::from_reader(&mut reader, &session, 3).unwrap();
+
+        assert_eq!(analysis.tank_capacity, 105.0);
+        assert_eq!(analysis.ticks.len(), 3371);
+        // The fixture is a single out lap, fuel only goes down.
+        assert_eq!(analysis.ticks[0].fuel_level, 48.996525);
+        assert_eq!(analysis.ticks[3370].fuel_level, 48.130154);
+        assert!(analysis.ticks[0].laps_remaining.is_none());
+        let tick = &analysis.ticks[2000];
+        assert!((tick.use_per_hour - 122.12).abs() < 0.01);
+        assert!((tick.laps_remaining.unwrap() - 15.10).abs() < 0.01);
+        assert!(analysis.laps.is_empty());
+        assert!(analysis.refuels.is_empty());
+        assert!(analysis.stint_plan(RaceLength::Laps(10)).is_none());
+
+        // only the samples of the session are read
+        let later = SessionSamples {
+            start: 2000,
+            ..session
+        };
+        let later_analysis = FuelAnalysis::from_reader(&mut reader, &later, 3).unwrap();
+        assert_eq!(later_analysis.ticks.len(), 1371);
+        assert_eq!(
+            later_analysis.ticks[0].fuel_level,
+            analysis.ticks[2000].fuel_level
+        );
+
+        analysis.laps = [3.0, 3.2, 3.4, 20.0]
+            .iter()
+            .enumerate()
+            .map(|(lap, used)| LapFuel {
+                lap: lap as i32 + 1,
+                lap_time: 90.0,
+                used: *used,
+                refuelled: *used > 10.0,
+            })
+            .collect();
+        assert_eq!(analysis.rolling_average()[1], Some(3.1));
+        assert_eq!(analysis.rolling_average()[3], Some(3.2));
+
+        let plan = analysis.stint_plan(RaceLength::Time(3600.0)).unwrap();
+        assert_eq!(plan.laps, 40);
+        assert_eq!(plan.stints.len(), 2);
+        assert_eq!(plan.stints[0].laps, 32);
+        assert_eq!(plan.stints[1].laps, 8);
+    }
+
+    #[test]
+    fn test_fuel_laps_and_refuels() {
+        let reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let mut analysis = FuelAnalysis::new(&reader.session_info.driver_info, 3);
+        let sample = |session_time: f64, lap: i32, fuel_level: f32, on_pit_road: bool| FuelSample {
+            session_time,
+            lap,
+            fuel_level,
+            fuel_use_per_hour: 90.0,
+            on_pit_road,
+        };
+        analysis.push(sample(0.0, 1, 50.0, false));
+        // the first lap is only known from its end, so it isn't counted
+        analysis.push(sample(90.0, 2, 47.0, false));
+        analysis.push(sample(180.0, 3, 44.0, false));
+        analysis.push(sample(250.0, 3, 42.0, true));
+        analysis.push(sample(260.0, 3, 60.0, true));
+        analysis.push(sample(270.0, 3, 60.0, false));
+        analysis.push(sample(300.0, 4, 58.0, false));
+
+        let laps: Vec<(i32, f64, f32, bool)> = analysis
+            .laps
+            .iter()
+            .map(|lap| (lap.lap, lap.lap_time, lap.used, lap.refuelled))
+            .collect();
+        assert_eq!(laps, vec![(2, 90.0, 3.0, false), (3, 120.0, 4.0, true)]);
+        assert_eq!(analysis.refuels.len(), 1);
+        assert_eq!(analysis.refuels[0].lap, 3);
+        assert_eq!(analysis.refuels[0].session_time, 270.0);
+        assert_eq!(analysis.refuels[0].amount, 18.0);
+        // refuelled laps are left out of the average
+        assert_eq!(analysis.average_per_lap(), Some(3.0));
+        assert_eq!(analysis.ticks[6].laps_remaining, Some(58.0 / 3.0));
+    }
+}
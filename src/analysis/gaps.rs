@@ -0,0 +1,284 @@
+use crate::{session_info::DriverInfo, IbtReader, SessionSamples};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GapMethod {
+    // Distance between the cars times the class' estimated lap time.
+    Estimated,
+    // How long ago the car ahead was where the car behind is now, exact
+    // but only known once the car ahead has been there.
+    Interpolated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GapTarget {
+    // Signed, positive when the car is behind the other one.
+    Car(i32),
+    Leader,
+    Ahead,
+    Behind,
+}
+
+// Every car's progress (laps + lap distance) through a session, from which
+// the time gaps between any two cars can be worked out at each tick.
+pub struct Gaps {
+    pub times: Vec<f64>,
+    // per car idx, the furthest the car got so far, which keeps it ordered
+    // for searching
+    reached: Vec<Vec<f64>>,
+    // per car idx, the ticks where its progress started over, e.g. in the
+    // next session, `reached` is only ordered from one to the next
+    restarts: Vec<Vec<usize>>,
+    in_world: Vec<Vec<bool>>,
+    est_lap_times: Vec<f64>,
+}
+
+impl Gaps {
+    pub fn new(driver_info: &DriverInfo) -> Gaps {
+        let cars = driver_info
+            .drivers
+            .iter()
+            .map(|driver| driver.car_idx as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut est_lap_times = vec![0.0; cars];
+        for driver in &driver_info.drivers {
+            est_lap_times[driver.car_idx as usize] = driver.car_class_est_lap_time as f64;
+        }
+        Gaps {
+            times: vec![],
+            reached: vec![vec![]; cars],
+            restarts: vec![vec![]; cars],
+            in_world: vec![vec![]; cars],
+            est_lap_times,
+        }
+    }
+
+    // None when the file doesn't have the CarIdx* arrays. Only the samples of
+    // `session` are read.
+    pub fn from_reader(reader: &mut IbtReader, session: &SessionSamples) -> Option<Gaps> {
+        let session_time = reader.find_var("SessionTime".to_string())?;
+        let lap = reader.find_var("CarIdxLap".to_string())?;
+        let lap_dist_pct = reader.find_var("CarIdxLapDistPct".to_string())?;
+
+        let mut gaps = Gaps::new(&reader.session_info.driver_info);
+        let cars = gaps.reached.len();
+        for sample in reader.session_samples(session) {
+            let time = sample.get_by_header(&session_time).unwrap().float_64();
+            let positions: Vec<Option<(i32, f32)>> = (0..cars)
+                .map(|idx| {
+                    let lap = sample.get_by_header_at(&lap, idx)?.int();
+                    let pct = sample.get_by_header_at(&lap_dist_pct, idx)?.float_32();
+                    Some((lap, pct))
+                })
+                .collect();
+            gaps.push(time, &positions);
+        }
+        Some(gaps)
+    }
+
+    // Adds one tick, `cars` holds `CarIdxLap` and `CarIdxLapDistPct` by car
+    // idx, negative values mean the car isn't in the world. A car that's
+    // more than a lap behind where it was is in a new session.
+    pub fn push(&mut self, session_time: f64, cars: &[Option<(i32, f32)>]) {
+        self.times.push(session_time);
+        let tick = self.times.len() - 1;
+        for (idx, ((reached, in_world), restarts)) in self
+            .reached
+            .iter_mut()
+            .zip(&mut self.in_world)
+            .zip(&mut self.restarts)
+            .enumerate()
+        {
+            let mut previous = reached.last().copied().unwrap_or(f64::NEG_INFINITY);
+            match cars.get(idx).copied().flatten() {
+                Some((lap, pct)) if lap >= 0 && pct >= 0.0 => {
+                    let mut progress = lap as f64 + pct as f64;
+                    if previous - progress > 1.0 {
+                        restarts.push(tick);
+                        previous = f64::NEG_INFINITY;
+                    }
+                    // `CarIdxLap` and `CarIdxLapDistPct` don't always roll
+                    // over on the same tick.
+                    if previous.is_finite() {
+                        if progress - previous > 0.5 && pct > 0.5 {
+                            progress -= 1.0;
+                        } else if previous - progress > 0.5 && pct < 0.5 {
+                            progress += 1.0;
+                        }
+                    }
+                    reached.push(progress.max(previous));
+                    in_world.push(true);
+                }
+                _ => {
+                    reached.push(previous);
+                    in_world.push(false);
+                }
+            }
+        }
+    }
+
+    // Gap time series for `car_idx`, only ticks where it's known.
+    pub fn gap(&self, car_idx: i32, target: GapTarget, method: GapMethod) -> Vec<(f64, f64)> {
+        let car = car_idx as usize;
+        if car >= self.reached.len() {
+            return vec![];
+        }
+        (0..self.times.len())
+            .filter_map(|tick| {
+                let gap = match self.target(car, target, tick)? {
+                    // the car behind's gap to us
+                    (other, true) => self.gap_at(other, car, tick, method),
+                    (other, false) => self.gap_at(car, other, tick, method),
+                }?;
+                Some((self.times[tick], gap))
+            })
+            .collect()
+    }
+
+    // The other car, and whether it's the one behind.
+    fn target(&self, car: usize, target: GapTarget, tick: usize) -> Option<(usize, bool)> {
+        if !self.in_world[car][tick] {
+            return None;
+        }
+        let progress = self.reached[car][tick];
+        let others = (0..self.reached.len())
+            .filter(|other| *other != car && self.in_world[*other][tick])
+            .map(|other| (other, self.reached[other][tick]));
+        match target {
+            GapTarget::Car(other) => Some((other as usize, false)),
+            GapTarget::Leader => others
+                .filter(|(_, other)| *other > progress)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(other, _)| (other, false)),
+            GapTarget::Ahead => others
+                .filter(|(_, other)| *other > progress)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(other, _)| (other, false)),
+            GapTarget::Behind => others
+                .filter(|(_, other)| *other < progress)
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(other, _)| (other, true)),
+        }
+    }
+
+    // How far `car` is behind `other`, negative when it's ahead.
+    fn gap_at(&self, car: usize, other: usize, tick: usize, method: GapMethod) -> Option<f64> {
+        if other >= self.reached.len() || !self.in_world[other][tick] {
+            return None;
+        }
+        let (progress, other_progress) = (self.reached[car][tick], self.reached[other][tick]);
+        match method {
+            GapMethod::Estimated => {
+                let lap_time = self.est_lap_times[car];
+                (lap_time > 0.0).then_some((other_progress - progress) * lap_time)
+            }
+            GapMethod::Interpolated if other_progress >= progress => {
+                let time = self.time_reached(other, progress, tick)?;
+                Some(self.times[tick] - time)
+            }
+            GapMethod::Interpolated => {
+                let time = self.time_reached(car, other_progress, tick)?;
+                Some(time - self.times[tick])
+            }
+        }
+    }
+
+    // When `car` got to `progress`, looking no further than `tick` and no
+    // further back than the car's last restart.
+    fn time_reached(&self, car: usize, progress: f64, tick: usize) -> Option<f64> {
+        let restarts = &self.restarts[car];
+        let start = restarts[..restarts.partition_point(|restart| *restart <= tick)]
+            .last()
+            .copied()
+            .unwrap_or(0);
+        let reached = &self.reached[car][start..=tick];
+        let times = &self.times[start..=tick];
+        let after = reached.partition_point(|reached| *reached < progress);
+        if after == reached.len() {
+            return None;
+        }
+        if reached[after] == progress {
+            return Some(times[after]);
+        }
+        let before = after.checked_sub(1)?;
+        if !reached[before].is_finite() {
+            return None;
+        }
+        let ratio = (progress - reached[before]) / (reached[after] - reached[before]);
+        Some(times[before] + ratio * (times[after] - times[before]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let session = reader.sessions().remove(0);
+        assert!(Gaps::from_reader(&mut reader, &session).is_none());
+
+        let mut driver_info = reader.session_info.driver_info;
+        let mut second = driver_info.drivers[0].clone();
+        second.car_idx = 1;
+        driver_info.drivers.push(second);
+        let est_lap_time = driver_info.drivers[0].car_class_est_lap_time as f64;
+
+        // Car 0 leads car 1 by a tenth of a lap, both at exactly one lap per
+        // estimated lap time. Car 1 joins half way.
+        let mut gaps = Gaps::new(&driver_info);
+        for tick in 0..=60 {
+            let time = tick as f64 * est_lap_time / 20.0;
+            let progress = |start: f64| {
+                let progress = start + time / est_lap_time;
+                Some((progress.floor() as i32, progress.fract() as f32))
+            };
+            let behind = if tick < 30 { None } else { progress(0.9) };
+            gaps.push(time, &[progress(1.0), behind]);
+        }
+
+        let estimated = gaps.gap(1, GapTarget::Leader, GapMethod::Estimated);
+        assert_eq!(estimated.len(), 31);
+        assert!(estimated
+            .iter()
+            .all(|(_, gap)| (gap - est_lap_time / 10.0).abs() < 1e-3));
+
+        let interpolated = gaps.gap(1, GapTarget::Ahead, GapMethod::Interpolated);
+        assert_eq!(interpolated.len(), 31);
+        assert!(interpolated
+            .iter()
+            .all(|(_, gap)| (gap - est_lap_time / 10.0).abs() < 1e-3));
+
+        let behind = gaps.gap(0, GapTarget::Behind, GapMethod::Interpolated);
+        assert_eq!(behind, interpolated);
+        let to_car = gaps.gap(0, GapTarget::Car(1), GapMethod::Interpolated);
+        assert!((to_car[0].1 + est_lap_time / 10.0).abs() < 1e-3);
+        assert!(gaps
+            .gap(0, GapTarget::Ahead, GapMethod::Estimated)
+            .is_empty());
+
+        // The next session starts from lap 0 again, with car 1 still a tenth
+        // of a lap behind.
+        for tick in 0..20 {
+            let time = (61 + tick) as f64 * est_lap_time / 20.0;
+            let progress = |start: f64| {
+                let progress = start + tick as f64 / 20.0;
+                Some((progress.floor() as i32, progress.fract() as f32))
+            };
+            gaps.push(time, &[progress(0.2), progress(0.1)]);
+        }
+        let estimated = gaps.gap(1, GapTarget::Leader, GapMethod::Estimated);
+        assert_eq!(estimated.len(), 51);
+        assert!(estimated
+            .iter()
+            .all(|(_, gap)| (gap - est_lap_time / 10.0).abs() < 1e-3));
+        // car 0 got to where car 1 is in the previous session, which doesn't
+        // count, so the first two ticks aren't known
+        let interpolated = gaps.gap(1, GapTarget::Ahead, GapMethod::Interpolated);
+        assert_eq!(interpolated.len(), 49);
+        assert!(interpolated
+            .iter()
+            .all(|(_, gap)| (gap - est_lap_time / 10.0).abs() < 1e-3));
+    }
+}
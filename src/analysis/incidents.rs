@@ -0,0 +1,145 @@
+use crate::{
+    constants::{Flags, TrackLocation},
+    IbtReader, SessionSamples,
+};
+
+// Yaw rate (rad/s) above which the car is considered to be spinning.
+pub const DEFAULT_SPIN_YAW_RATE: f32 = 1.5;
+
+#[derive(Debug, PartialEq)]
+pub enum NotableEventKind {
+    Incident {
+        points: i32,
+    },
+    OffTrack {
+        duration: f64,
+    },
+    Spin {
+        max_yaw_rate: f32,
+    },
+    FlagChange {
+        raised: Vec<Flags>,
+        cleared: Vec<Flags>,
+    },
+}
+
+#[derive(Debug)]
+pub struct NotableEvent {
+    pub kind: NotableEventKind,
+    pub session_time: f64,
+    pub lap: i32,
+    pub lap_dist_pct: f32,
+}
+
+pub fn notable_events(
+    reader: &mut IbtReader,
+    session: &SessionSamples,
+    spin_yaw_rate: f32,
+) -> Option<Vec<NotableEvent>> {
+    let session_time = reader.find_var("SessionTime".to_string())?;
+    let lap = reader.find_var("Lap".to_string())?;
+    let lap_dist_pct = reader.find_var("LapDistPct".to_string())?;
+    let incidents = reader
+        .find_var("PlayerCarMyIncidentCount".to_string())
+        .or_else(|| reader.find_var("PlayerCarDriverIncidentCount".to_string()))?;
+    let track_surface = reader.find_var("PlayerTrackSurface".to_string())?;
+    let yaw_rate = reader.find_var("YawRate".to_string())?;
+    let session_flags = reader.find_var("SessionFlags".to_string())?;
+
+    let mut events: Vec<NotableEvent> = vec![];
+    let mut previous_incidents: Option<i32> = None;
+    let mut previous_flags: Option<u32> = None;
+    // index into `events` of the off track or spin that is still going on
+    let mut off_track: Option<usize> = None;
+    let mut spin: Option<usize> = None;
+
+    for sample in reader.session_samples(session) {
+        let time = sample.get_by_header(&session_time).unwrap().float_64();
+        let event = |kind| NotableEvent {
+            kind,
+            session_time: time,
+            lap: sample.get_by_header(&lap).unwrap().int(),
+            lap_dist_pct: sample.get_by_header(&lap_dist_pct).unwrap().float_32(),
+        };
+
+        let count = sample.get_by_header(&incidents).unwrap().int();
+        if let Some(previous) = previous_incidents.filter(|previous| count > *previous) {
+            events.push(event(NotableEventKind::Incident {
+                points: count - previous,
+            }));
+        }
+        previous_incidents = Some(count);
+
+        let location = TrackLocation::try_from(sample.get_by_header(&track_surface).unwrap().int());
+        match (location == Ok(TrackLocation::OffTrack), off_track) {
+            (true, None) => {
+                off_track = Some(events.len());
+                events.push(event(NotableEventKind::OffTrack { duration: 0.0 }));
+            }
+            (true, Some(index)) => {
+                let start = events[index].session_time;
+                events[index].kind = NotableEventKind::OffTrack {
+                    duration: time - start,
+                };
+            }
+            (false, _) => off_track = None,
+        }
+
+        let rate = sample.get_by_header(&yaw_rate).unwrap().float_32().abs();
+        match (rate > spin_yaw_rate, spin) {
+            (true, None) => {
+                spin = Some(events.len());
+                events.push(event(NotableEventKind::Spin { max_yaw_rate: rate }));
+            }
+            (true, Some(index)) => {
+                if let NotableEventKind::Spin { max_yaw_rate } = &mut events[index].kind {
+                    *max_yaw_rate = max_yaw_rate.max(rate);
+                }
+            }
+            (false, _) => spin = None,
+        }
+
+        let flags = sample.get_by_header(&session_flags).unwrap().bitfield();
+        if let Some(previous) = previous_flags.filter(|previous| *previous != flags) {
+            events.push(event(NotableEventKind::FlagChange {
+                raised: Flags::from_bits(flags & !previous),
+                cleared: Flags::from_bits(previous & !flags),
+            }));
+        }
+        previous_flags = Some(flags);
+    }
+
+    Some(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_notable_events() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let session = reader.sessions().remove(0);
+        let events = notable_events(&mut reader, &session, DEFAULT_SPIN_YAW_RATE).unwrap();
+
+        let incidents: Vec<&NotableEvent> = events
+            .iter()
+            .filter(|event| matches!(event.kind, NotableEventKind::Incident { .. }))
+            .collect();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].kind, NotableEventKind::Incident { points: 2 });
+
+        let off_tracks: Vec<&NotableEvent> = events
+            .iter()
+            .filter(|event| matches!(event.kind, NotableEventKind::OffTrack { .. }))
+            .collect();
+        assert_eq!(off_tracks.len(), 1);
+        assert!(off_tracks[0].session_time > 103.0 && off_tracks[0].session_time < 105.0);
+
+        assert!(events
+            .iter()
+            .any(|event| matches!(event.kind, NotableEventKind::Spin { .. })));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod driver_stints;
+pub mod fuel;
+pub mod gaps;
+pub mod incidents;
+pub mod pit_stops;
+pub mod race;
+pub mod track_map;
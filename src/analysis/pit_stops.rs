@@ -0,0 +1,270 @@
+use crate::{constants::PitFlags, IbtReader, SessionSamples};
+
+// Below this speed (m/s) the car is considered stopped in its pit stall.
+const STATIONARY_SPEED: f32 = 0.5;
+
+pub struct PitStop {
+    pub lap: i32,
+    pub entry_time: f64,
+    pub exit_time: f64,
+    pub stationary_time: f64,
+    // Time of the in and out laps compared to a typical lap, only known when
+    // both laps were completed and there are laps without pit stops to compare to.
+    pub time_lost: Option<f64>,
+    pub services: Vec<PitFlags>,
+    pub fuel_requested: f32,
+    pub fuel_added: f32,
+    pub service_status: i32,
+}
+
+impl PitStop {
+    pub fn pit_lane_time(&self) -> f64 {
+        self.exit_time - self.entry_time
+    }
+}
+
+// One tick of the player car's pit telemetry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PitTick {
+    pub session_time: f64,
+    pub lap: i32,
+    pub on_pit_road: bool,
+    pub in_pit_stall: bool,
+    // m/s
+    pub speed: f32,
+    pub fuel_level: f32,
+    // `PitSvFlags`, `PitSvFuel` and `PlayerCarPitSvStatus`
+    pub service_flags: u32,
+    pub fuel_requested: f32,
+    pub service_status: i32,
+}
+
+struct LapTime {
+    lap: i32,
+    start: f64,
+    end: f64,
+    pitted: bool,
+}
+
+// Finds the player's pit stops, what was serviced and how much time they
+// cost compared to a typical lap.
+pub struct PitStops {
+    pub stops: Vec<PitStop>,
+    laps: Vec<LapTime>,
+    current: Option<PitStop>,
+    fuel_at_stall: Option<f32>,
+    previous: Option<(f64, bool)>,
+}
+
+pub fn pit_stops(reader: &mut IbtReader, session: &SessionSamples) -> Option<Vec<PitStop>> {
+    Some(PitStops::from_reader(reader, session)?.finish())
+}
+
+impl Default for PitStops {
+    fn default() -> PitStops {
+        PitStops::new()
+    }
+}
+
+impl PitStops {
+    pub fn new() -> PitStops {
+        PitStops {
+            stops: vec![],
+            laps: vec![],
+            current: None,
+            fuel_at_stall: None,
+            previous: None,
+        }
+    }
+
+    // The stops made during `session`, in and out laps are compared to
+    // other laps of the same session.
+    pub fn from_reader(reader: &mut IbtReader, session: &SessionSamples) -> Option<PitStops> {
+        let session_time = reader.find_var("SessionTime".to_string())?;
+        let lap = reader.find_var("Lap".to_string())?;
+        let on_pit_road = reader.find_var("OnPitRoad".to_string())?;
+        let in_pit_stall = reader.find_var("PlayerCarInPitStall".to_string())?;
+        let flags = reader.find_var("PitSvFlags".to_string())?;
+        let fuel = reader.find_var("PitSvFuel".to_string())?;
+        let status = reader
+            .find_var("PlayerCarPitSvStatus".to_string())
+            .or_else(|| reader.find_var("PitSvStatus".to_string()))?;
+        let speed = reader.find_var("Speed".to_string())?;
+        let fuel_level = reader.find_var("FuelLevel".to_string())?;
+
+        let mut stops = PitStops::new();
+        for sample in reader.session_samples(session) {
+            stops.push(PitTick {
+                session_time: sample.get_by_header(&session_time).unwrap().float_64(),
+                lap: sample.get_by_header(&lap).unwrap().int(),
+                on_pit_road: sample.get_by_header(&on_pit_road).unwrap().bool(),
+                in_pit_stall: sample.get_by_header(&in_pit_stall).unwrap().bool(),
+                speed: sample.get_by_header(&speed).unwrap().float_32(),
+                fuel_level: sample.get_by_header(&fuel_level).unwrap().float_32(),
+                service_flags: sample.get_by_header(&flags).unwrap().bitfield(),
+                fuel_requested: sample.get_by_header(&fuel).unwrap().float_32(),
+                service_status: sample.get_by_header(&status).unwrap().int(),
+            });
+        }
+        Some(stops)
+    }
+
+    pub fn push(&mut self, tick: PitTick) {
+        let time = tick.session_time;
+        let in_pits = tick.on_pit_road;
+
+        match self.laps.last_mut() {
+            Some(last) if last.lap == tick.lap => last.pitted |= in_pits,
+            last => {
+                // a lap ends when the next one starts, not on its own last tick
+                if let Some(last) = last {
+                    last.end = time;
+                }
+                self.laps.push(LapTime {
+                    lap: tick.lap,
+                    start: time,
+                    end: time,
+                    pitted: in_pits,
+                });
+            }
+        }
+
+        // Only count stops where we've seen the car enter the pit lane, files
+        // recorded from the pit box start with the car already on pit road.
+        let entered = self.previous.is_some_and(|(_, was_in_pits)| !was_in_pits) && in_pits;
+        if entered {
+            self.current = Some(PitStop {
+                lap: tick.lap,
+                entry_time: time,
+                exit_time: time,
+                stationary_time: 0.0,
+                time_lost: None,
+                services: vec![],
+                fuel_requested: 0.0,
+                fuel_added: 0.0,
+                service_status: 0,
+            });
+        }
+
+        if let Some(stop) = self.current.as_mut() {
+            if tick.in_pit_stall {
+                if tick.speed < STATIONARY_SPEED {
+                    if let Some((previous_time, _)) = self.previous {
+                        stop.stationary_time += time - previous_time;
+                    }
+                }
+                if self.fuel_at_stall.is_none() {
+                    self.fuel_at_stall = Some(tick.fuel_level);
+                    stop.services = PitFlags::from_bits(tick.service_flags);
+                    stop.fuel_requested = tick.fuel_requested;
+                }
+                stop.service_status = tick.service_status;
+            }
+            if let Some(start_level) = self.fuel_at_stall {
+                stop.fuel_added = stop.fuel_added.max(tick.fuel_level - start_level);
+            }
+            if !in_pits {
+                stop.exit_time = time;
+                self.stops.push(self.current.take().unwrap());
+                self.fuel_at_stall = None;
+            }
+        }
+
+        self.previous = Some((time, in_pits));
+    }
+
+    // Works out the time lost once every lap is known. A stop the car hadn't
+    // left the pit lane from when the recording ended is left out.
+    pub fn finish(mut self) -> Vec<PitStop> {
+        let laps = &self.laps;
+        // The first and last laps were cut short by the start or the end of
+        // the recording so they can't be compared to a typical lap.
+        let complete = |index: usize| index > 0 && index + 1 < laps.len();
+        let mut clean: Vec<f64> = (0..laps.len())
+            .filter(|index| complete(*index) && !laps[*index].pitted)
+            .map(|index| laps[index].end - laps[index].start)
+            .collect();
+        clean.sort_by(f64::total_cmp);
+        let typical = clean.get(clean.len() / 2).copied();
+
+        for stop in self.stops.iter_mut() {
+            let affected: Vec<usize> = (0..laps.len())
+                .filter(|index| {
+                    let lap = &laps[*index];
+                    lap.pitted && lap.end >= stop.entry_time && lap.start <= stop.exit_time
+                })
+                .collect();
+            stop.time_lost = match typical {
+                Some(typical) if affected.iter().all(|index| complete(*index)) => Some(
+                    affected
+                        .iter()
+                        .map(|index| laps[*index].end - laps[*index].start - typical)
+                        .sum(),
+                ),
+                _ => None,
+            };
+        }
+        self.stops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_pit_stops() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+
+        // the car leaves the pit box but never comes back in
+        let session = reader.sessions().remove(0);
+        let stops = pit_stops(&mut reader, &session).unwrap();
+        assert!(stops.is_empty());
+
+        assert_eq!(
+            PitFlags::from_bits(0x0011),
+            vec![PitFlags::LFTireChange, PitFlags::FuelFill]
+        );
+
+        // Laps start at these times, lap 3 is the in lap and lap 4 the out
+        // lap. Ticks are a second apart except for a few dropped at the end
+        // of the out lap.
+        let lap_starts = [0.0, 100.0, 200.0, 310.0, 440.0, 540.0, 640.0];
+        let mut stops = PitStops::new();
+        for second in (0..=650).filter(|second| !(435..440).contains(second)) {
+            let time = second as f64;
+            let lap = lap_starts.iter().filter(|start| **start <= time).count() as i32;
+            stops.push(PitTick {
+                session_time: time,
+                lap,
+                on_pit_road: (280..330).contains(&second),
+                in_pit_stall: (290..=311).contains(&second),
+                speed: if (292..=308).contains(&second) {
+                    0.0
+                } else {
+                    20.0
+                },
+                fuel_level: (10.0 + 2.0 * (time - 295.0).max(0.0)).min(40.0) as f32,
+                service_flags: PitFlags::FuelFill as u32,
+                fuel_requested: 30.0,
+                service_status: 1,
+            });
+        }
+        let stops = stops.finish();
+        assert_eq!(stops.len(), 1);
+        let stop = &stops[0];
+        assert_eq!(stop.lap, 3);
+        assert_eq!(stop.entry_time, 280.0);
+        assert_eq!(stop.exit_time, 330.0);
+        assert_eq!(stop.pit_lane_time(), 50.0);
+        assert_eq!(stop.stationary_time, 17.0);
+        assert_eq!(stop.services, vec![PitFlags::FuelFill]);
+        assert_eq!(stop.fuel_requested, 30.0);
+        assert_eq!(stop.fuel_added, 30.0);
+        assert_eq!(stop.service_status, 1);
+        // 110 s in lap and 130 s out lap against 100 s laps
+        assert_eq!(stop.time_lost, Some(40.0));
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    headers::VarHeader,
+    samples::Sample,
+    session_info::{Driver, DriverInfo},
+    IbtReader, SessionSamples,
+};
+
+pub const DEFAULT_HISTORY_INTERVAL: f64 = 1.0;
+
+// What the CarIdx* arrays say about one car at one tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CarIdxTick {
+    // -1 while the car isn't in the world
+    pub lap: i32,
+    pub lap_completed: i32,
+    pub lap_dist_pct: f32,
+    pub position: i32,
+    pub class_position: i32,
+    pub on_pit_road: bool,
+    pub last_lap_time: f32,
+    pub best_lap_time: f32,
+    // gap to the leader during races, to the pole time otherwise
+    pub f2_time: f32,
+}
+
+pub struct CompetitorLap {
+    pub lap: i32,
+    // session time when the lap was completed
+    pub end_time: f64,
+    // `CarIdxLastLapTime` once it reported this lap, otherwise the time
+    // since the previous lap was completed
+    pub time: Option<f32>,
+    pub position: i32,
+    // the car was on pit road at some point during the lap
+    pub pitted: bool,
+}
+
+pub struct CompetitorPitStop {
+    pub lap: i32,
+    pub entry_time: f64,
+    pub exit_time: Option<f64>,
+}
+
+pub struct CarState {
+    pub session_time: f64,
+    pub lap: i32,
+    pub lap_dist_pct: f32,
+    pub position: i32,
+    pub class_position: i32,
+    pub gap: f32,
+}
+
+pub struct Competitor {
+    pub car_idx: i32,
+    pub driver: Driver,
+    pub laps: Vec<CompetitorLap>,
+    pub pit_stops: Vec<CompetitorPitStop>,
+    // one entry per history interval while the car is in the world
+    pub history: Vec<CarState>,
+    pub best_lap_time: Option<f32>,
+    last: Option<CarIdxTick>,
+    // `CarIdxLastLapTime` before the latest lap was completed
+    previous_last_lap_time: f32,
+    pitted: bool,
+}
+
+// Every competitor's laps, pit stops, positions and gaps through a session,
+// rebuilt from the CarIdx* arrays.
+pub struct Race {
+    pub competitors: Vec<Competitor>,
+    interval: f64,
+    next_history: f64,
+}
+
+struct CarIdxVars {
+    lap: VarHeader,
+    lap_completed: VarHeader,
+    lap_dist_pct: VarHeader,
+    position: VarHeader,
+    class_position: VarHeader,
+    on_pit_road: VarHeader,
+    last_lap_time: VarHeader,
+    best_lap_time: VarHeader,
+    f2_time: VarHeader,
+}
+
+impl Competitor {
+    pub fn position_at(&self, session_time: f64) -> Option<i32> {
+        self.history
+            .iter()
+            .take_while(|state| state.session_time <= session_time)
+            .last()
+            .map(|state| state.position)
+    }
+}
+
+impl Race {
+    // Pace cars and spectators are left out.
+    pub fn new(driver_info: &DriverInfo, interval: f64) -> Race {
+        Race {
+            competitors: driver_info
+                .drivers
+                .iter()
+                .filter(|driver| driver.car_is_pace_car == 0 && driver.is_spectator == 0)
+                .map(|driver| Competitor {
+                    car_idx: driver.car_idx,
+                    driver: driver.clone(),
+                    laps: vec![],
+                    pit_stops: vec![],
+                    history: vec![],
+                    best_lap_time: None,
+                    last: None,
+                    previous_last_lap_time: 0.0,
+                    pitted: false,
+                })
+                .collect(),
+            interval,
+            next_history: f64::MIN,
+        }
+    }
+
+    // None when the file doesn't have the CarIdx* arrays, which is the case
+    // for most files recorded to disk. Laps and positions start over with
+    // every session, so only the samples of `session` are read.
+    pub fn from_reader(
+        reader: &mut IbtReader,
+        session: &SessionSamples,
+        interval: f64,
+    ) -> Option<Race> {
+        let session_time = reader.find_var("SessionTime".to_string())?;
+        let vars = CarIdxVars {
+            lap: reader.find_var("CarIdxLap".to_string())?,
+            lap_completed: reader.find_var("CarIdxLapCompleted".to_string())?,
+            lap_dist_pct: reader.find_var("CarIdxLapDistPct".to_string())?,
+            position: reader.find_var("CarIdxPosition".to_string())?,
+            class_position: reader.find_var("CarIdxClassPosition".to_string())?,
+            on_pit_road: reader.find_var("CarIdxOnPitRoad".to_string())?,
+            last_lap_time: reader.find_var("CarIdxLastLapTime".to_string())?,
+            best_lap_time: reader.find_var("CarIdxBestLapTime".to_string())?,
+            f2_time: reader.find_var("CarIdxF2Time".to_string())?,
+        };
+
+        let mut race = Race::new(&reader.session_info.driver_info, interval);
+        let cars = vars.lap.count.max(1) as usize;
+        for sample in reader.session_samples(session) {
+            let time = sample.get_by_header(&session_time).unwrap().float_64();
+            let ticks: Vec<CarIdxTick> = (0..cars).map(|idx| vars.read(&sample, idx)).collect();
+            race.push(time, &ticks);
+        }
+        race.finish();
+        Some(race)
+    }
+
+    pub fn competitor(&self, car_idx: i32) -> Option<&Competitor> {
+        self.competitors
+            .iter()
+            .find(|competitor| competitor.car_idx == car_idx)
+    }
+
+    // Adds one tick, `cars` is indexed by car idx.
+    pub fn push(&mut self, session_time: f64, cars: &[CarIdxTick]) {
+        let record_history = session_time >= self.next_history;
+        if record_history {
+            self.next_history = session_time + self.interval;
+        }
+
+        for competitor in self.competitors.iter_mut() {
+            let tick = match cars.get(competitor.car_idx as usize) {
+                Some(tick) if tick.lap >= 0 => *tick,
+                _ => continue,
+            };
+
+            competitor.pitted |= tick.on_pit_road;
+            if let Some(last) = competitor.last {
+                if tick.lap_completed > last.lap_completed && last.lap_completed >= 0 {
+                    competitor.laps.push(CompetitorLap {
+                        lap: tick.lap_completed,
+                        end_time: session_time,
+                        time: None,
+                        position: tick.position,
+                        pitted: competitor.pitted,
+                    });
+                    competitor.previous_last_lap_time = last.last_lap_time;
+                    competitor.pitted = tick.on_pit_road;
+                }
+                if tick.on_pit_road && !last.on_pit_road {
+                    competitor.pit_stops.push(CompetitorPitStop {
+                        lap: tick.lap,
+                        entry_time: session_time,
+                        exit_time: None,
+                    });
+                }
+                if !tick.on_pit_road && last.on_pit_road {
+                    if let Some(stop) = competitor.pit_stops.last_mut() {
+                        stop.exit_time.get_or_insert(session_time);
+                    }
+                }
+            }
+
+            // The lap time is published a moment after the line is crossed.
+            if let Some(lap) = competitor.laps.last_mut() {
+                if lap.time.is_none()
+                    && tick.last_lap_time > 0.0
+                    && tick.last_lap_time != competitor.previous_last_lap_time
+                {
+                    lap.time = Some(tick.last_lap_time);
+                }
+            }
+            if tick.best_lap_time > 0.0 {
+                competitor.best_lap_time = Some(tick.best_lap_time);
+            }
+            if record_history {
+                competitor.history.push(CarState {
+                    session_time,
+                    lap: tick.lap,
+                    lap_dist_pct: tick.lap_dist_pct,
+                    position: tick.position,
+                    class_position: tick.class_position,
+                    gap: tick.f2_time,
+                });
+            }
+            competitor.last = Some(tick);
+        }
+    }
+
+    // Fills lap times that were never reported from the time between laps.
+    pub fn finish(&mut self) {
+        for competitor in self.competitors.iter_mut() {
+            let mut previous_end: Option<f64> = None;
+            for lap in competitor.laps.iter_mut() {
+                if lap.time.is_none() {
+                    lap.time = previous_end.map(|end| (lap.end_time - end) as f32);
+                }
+                previous_end = Some(lap.end_time);
+            }
+        }
+    }
+}
+
+impl CarIdxVars {
+    fn read(&self, sample: &Sample, idx: usize) -> CarIdxTick {
+        let int = |var: &VarHeader| sample.get_by_header_at(var, idx).map_or(-1, |v| v.int());
+        let float = |var: &VarHeader| {
+            sample
+                .get_by_header_at(var, idx)
+                .map_or(-1.0, |v| v.float_32())
+        };
+        CarIdxTick {
+            lap: int(&self.lap),
+            lap_completed: int(&self.lap_completed),
+            lap_dist_pct: float(&self.lap_dist_pct),
+            position: int(&self.position),
+            class_position: int(&self.class_position),
+            on_pit_road: sample
+                .get_by_header_at(&self.on_pit_road, idx)
+                .is_some_and(|v| v.bool()),
+            last_lap_time: float(&self.last_lap_time),
+            best_lap_time: float(&self.best_lap_time),
+            f2_time: float(&self.f2_time),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(
+        lap_completed: i32,
+        pct: f32,
+        position: i32,
+        on_pit_road: bool,
+        last: f32,
+    ) -> CarIdxTick {
+        CarIdxTick {
+            lap: lap_completed + 1,
+            lap_completed,
+            lap_dist_pct: pct,
+            position,
+            class_position: position - 1,
+            on_pit_road,
+            last_lap_time: last,
+            best_lap_time: last,
+            f2_time: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_race() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let session = reader.sessions().remove(0);
+        assert!(Race::from_reader(&mut reader, &session, DEFAULT_HISTORY_INTERVAL).is_none());
+
+        let mut race = Race::new(&reader.session_info.driver_info, DEFAULT_HISTORY_INTERVAL);
+        assert_eq!(race.competitors.len(), 1);
+
+        let not_in_world = CarIdxTick {
+            lap: -1,
+            ..tick(0, 0.0, 0, false, 0.0)
+        };
+        race.push(0.0, &[not_in_world]);
+        race.push(0.5, &[tick(0, 0.9, 1, false, -1.0)]);
+        race.push(1.0, &[tick(1, 0.01, 1, false, -1.0)]);
+        race.push(95.0, &[tick(1, 0.95, 2, true, -1.0)]);
+        race.push(96.0, &[tick(2, 0.02, 2, true, 94.5)]);
+        race.push(97.0, &[tick(2, 0.05, 2, false, 94.5)]);
+        race.push(190.0, &[tick(3, 0.0, 1, false, 94.5)]);
+        race.finish();
+
+        let competitor = race.competitor(0).unwrap();
+        assert_eq!(competitor.driver.user_name, "Georgi Martsenkov");
+        let laps: Vec<(i32, Option<f32>, bool)> = competitor
+            .laps
+            .iter()
+            .map(|lap| (lap.lap, lap.time, lap.pitted))
+            .collect();
+        assert_eq!(
+            laps,
+            vec![
+                (1, None, false),
+                (2, Some(94.5), true),
+                (3, Some(94.0), true)
+            ]
+        );
+        assert_eq!(competitor.pit_stops.len(), 1);
+        assert_eq!(competitor.pit_stops[0].entry_time, 95.0);
+        assert_eq!(competitor.pit_stops[0].exit_time, Some(97.0));
+        assert_eq!(competitor.best_lap_time, Some(94.5));
+        assert_eq!(competitor.history.len(), 5);
+        assert_eq!(competitor.position_at(96.5), Some(2));
+        assert_eq!(competitor.position_at(200.0), Some(1));
+    }
+}
@@ -0,0 +1,265 @@
+use std::f64::consts::PI;
+
+use serde_json::json;
+
+use crate::{IbtReader, SessionSamples};
+
+// Metres per degree of latitude, close enough for the size of a race track.
+const METRES_PER_DEGREE: f64 = 111_320.0;
+
+#[derive(Debug, Clone)]
+pub struct TrackPoint {
+    pub session_time: f64,
+    pub lap_dist_pct: f32,
+    // metres east and north of where the lap started
+    pub x: f64,
+    pub y: f64,
+}
+
+pub struct LapOutline {
+    pub lap: i32,
+    pub points: Vec<TrackPoint>,
+    pub closed: bool,
+}
+
+pub struct TrackMap {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub laps: Vec<LapOutline>,
+}
+
+impl TrackMap {
+    // Dead reckons the car's position by integrating its velocity along the
+    // compass heading. `YawNorth` is used when it's recorded, otherwise the
+    // heading is derived from `Yaw` and the track's north offset. Each
+    // session is reckoned on its own as the car is reset between them.
+    pub fn from_reader(reader: &mut IbtReader, session: &SessionSamples) -> Option<TrackMap> {
+        let session_time = reader.find_var("SessionTime".to_string())?;
+        let lap = reader.find_var("Lap".to_string())?;
+        let lap_dist_pct = reader.find_var("LapDistPct".to_string())?;
+        let velocity_x = reader.find_var("VelocityX".to_string())?;
+        let velocity_y = reader.find_var("VelocityY".to_string())?;
+        let yaw_north = reader.find_var("YawNorth".to_string());
+        let yaw = reader.find_var("Yaw".to_string());
+        if yaw_north.is_none() && yaw.is_none() {
+            return None;
+        }
+
+        let weekend_info = &reader.session_info.weekend_info;
+        let north_offset = weekend_info.north_offset().unwrap_or(0.0);
+        let mut map = TrackMap {
+            latitude: weekend_info.latitude(),
+            longitude: weekend_info.longitude(),
+            laps: vec![],
+        };
+
+        let mut position = (0.0, 0.0);
+        let mut previous_time: Option<f64> = None;
+        for sample in reader.session_samples(session) {
+            let time = sample.get_by_header(&session_time).unwrap().float_64();
+            let lap_num = sample.get_by_header(&lap).unwrap().int();
+            let pct = sample.get_by_header(&lap_dist_pct).unwrap().float_32();
+            let heading = match &yaw_north {
+                Some(var) => sample.get_by_header(var).unwrap().float_32() as f64,
+                None => {
+                    north_offset
+                        - sample
+                            .get_by_header(yaw.as_ref().unwrap())
+                            .unwrap()
+                            .float_32() as f64
+                }
+            };
+            let forward = sample.get_by_header(&velocity_x).unwrap().float_32() as f64;
+            let left = sample.get_by_header(&velocity_y).unwrap().float_32() as f64;
+
+            if let Some(previous) = previous_time {
+                let dt = time - previous;
+                position.0 += (forward * heading.sin() - left * heading.cos()) * dt;
+                position.1 += (forward * heading.cos() + left * heading.sin()) * dt;
+            }
+            previous_time = Some(time);
+
+            let point = TrackPoint {
+                session_time: time,
+                lap_dist_pct: pct,
+                x: position.0,
+                y: position.1,
+            };
+            // a lap is only complete when we've seen the car cross the line at
+            // both its start and its end
+            let crossed = map.laps.last().is_some_and(|outline| lap_num > outline.lap);
+            let complete = crossed && map.laps.len() > 1;
+            match map.laps.last_mut() {
+                Some(outline) if outline.lap == lap_num => outline.points.push(point),
+                last => {
+                    if let Some(outline) = last.filter(|_| crossed) {
+                        outline.points.push(point.clone());
+                        outline.closed = complete;
+                    }
+                    map.laps.push(LapOutline {
+                        lap: lap_num,
+                        points: vec![point],
+                        closed: false,
+                    });
+                }
+            }
+        }
+
+        for outline in map.laps.iter_mut().filter(|outline| outline.closed) {
+            outline.close_loop();
+        }
+        Some(map)
+    }
+
+    // The first complete lap, or the longest recorded one when there are none.
+    pub fn reference_lap(&self) -> Option<&LapOutline> {
+        self.laps
+            .iter()
+            .find(|outline| outline.closed)
+            .or_else(|| self.laps.iter().max_by_key(|outline| outline.points.len()))
+    }
+
+    // Where on the reference lap a given `LapDistPct` is.
+    pub fn locate(&self, lap_dist_pct: f32) -> Option<(f64, f64)> {
+        self.reference_lap()?
+            .points
+            .iter()
+            .min_by(|a, b| {
+                (a.lap_dist_pct - lap_dist_pct)
+                    .abs()
+                    .total_cmp(&(b.lap_dist_pct - lap_dist_pct).abs())
+            })
+            .map(|point| (point.x, point.y))
+    }
+
+    pub fn to_svg(&self) -> String {
+        let points = self.laps.iter().flat_map(|outline| outline.points.iter());
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0_f64, 0.0_f64, 0.0_f64, 0.0_f64);
+        for point in points {
+            min_x = min_x.min(point.x);
+            max_x = max_x.max(point.x);
+            min_y = min_y.min(point.y);
+            max_y = max_y.max(point.y);
+        }
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.1} {:.1} {:.1} {:.1}\">\n",
+            min_x,
+            -max_y,
+            (max_x - min_x).max(1.0),
+            (max_y - min_y).max(1.0)
+        );
+        for outline in &self.laps {
+            let path: Vec<String> = outline
+                .points
+                .iter()
+                .enumerate()
+                // svg's y axis points down, ours points north
+                .map(|(index, point)| {
+                    let command = if index == 0 { "M" } else { "L" };
+                    format!("{command}{:.2},{:.2}", point.x, -point.y)
+                })
+                .collect();
+            let close = if outline.closed { " Z" } else { "" };
+            svg.push_str(&format!(
+                "  <path data-lap=\"{}\" d=\"{}{close}\" fill=\"none\" stroke=\"black\"/>\n",
+                outline.lap,
+                path.join(" ")
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // Coordinates are placed around the track's latitude and longitude from
+    // the session info, they won't line up exactly with satellite imagery.
+    pub fn to_geojson(&self) -> String {
+        let latitude = self.latitude.unwrap_or(0.0);
+        let longitude = self.longitude.unwrap_or(0.0);
+        let metres_per_degree_lon = METRES_PER_DEGREE * (latitude * PI / 180.0).cos();
+
+        let features: Vec<serde_json::Value> = self
+            .laps
+            .iter()
+            .map(|outline| {
+                let coordinates: Vec<[f64; 2]> = outline
+                    .points
+                    .iter()
+                    .map(|point| {
+                        [
+                            longitude + point.x / metres_per_degree_lon,
+                            latitude + point.y / METRES_PER_DEGREE,
+                        ]
+                    })
+                    .collect();
+                json!({
+                    "type": "Feature",
+                    "properties": { "lap": outline.lap, "closed": outline.closed },
+                    "geometry": { "type": "LineString", "coordinates": coordinates },
+                })
+            })
+            .collect();
+
+        json!({ "type": "FeatureCollection", "features": features }).to_string()
+    }
+}
+
+impl LapOutline {
+    // Integration drift leaves a gap between the start and the end of a lap,
+    // spread the correction over the lap by distance travelled.
+    fn close_loop(&mut self) {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (first.clone(), last.clone()),
+            _ => return,
+        };
+        let (error_x, error_y) = (last.x - first.x, last.y - first.y);
+        let count = self.points.len();
+        for (index, point) in self.points.iter_mut().enumerate() {
+            // the closing point wraps back to 0% so use its position in the lap
+            let progress = if index + 1 == count {
+                1.0
+            } else {
+                point.lap_dist_pct as f64
+            };
+            point.x -= error_x * progress;
+            point.y -= error_y * progress;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_track_map() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let lat = reader.find_var("Lat".to_string()).unwrap();
+        let lon = reader.find_var("Lon".to_string()).unwrap();
+        let samples: Vec<_> = reader.samples().collect();
+        let first = samples.first().unwrap();
+        let last = samples.last().unwrap();
+
+        let session = reader.sessions().remove(0);
+        let map = TrackMap::from_reader(&mut reader, &session).unwrap();
+        assert_eq!(map.laps.len(), 1);
+        assert!(!map.laps[0].closed);
+
+        // dead reckoning should end up roughly where the GPS says the car is
+        let gps_north = (last.get_by_header(&lat).unwrap().float_64()
+            - first.get_by_header(&lat).unwrap().float_64())
+            * METRES_PER_DEGREE;
+        let gps_east = (last.get_by_header(&lon).unwrap().float_64()
+            - first.get_by_header(&lon).unwrap().float_64())
+            * METRES_PER_DEGREE
+            * (47.22_f64 * PI / 180.0).cos();
+        let end = map.laps[0].points.last().unwrap();
+        assert!((end.x - gps_east).abs() < 10.0);
+        assert!((end.y - gps_north).abs() < 10.0);
+
+        assert!(map.to_svg().starts_with("<svg"));
+        assert!(map.to_geojson().contains("\"LineString\""));
+    }
+}
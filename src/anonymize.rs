@@ -0,0 +1,225 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use yore::code_pages::CP1252;
+
+use crate::{
+    error::Error, read_bytes_file, session_info::DriverInfo, writer::write_ibt, IbtReader,
+};
+
+// Values every driver gets instead of their own.
+const I_RATING: &str = "0";
+const LIC_LEVEL: &str = "0";
+const LIC_SUB_LEVEL: &str = "0";
+const LIC_STRING: &str = "R 0.00";
+const LIC_COLOR: &str = "0xffffff";
+
+// Replaces personal details in the session info (names, ids, ratings,
+// licences and paint schemes) with pseudonyms and writes the file again.
+// Pseudonyms are derived from the iRacing ids and the salt, so the same
+// driver gets the same pseudonym in every file anonymized with one salt.
+pub struct Anonymizer {
+    salt: String,
+}
+
+impl Anonymizer {
+    pub fn new(salt: &str) -> Anonymizer {
+        Anonymizer {
+            salt: salt.to_string(),
+        }
+    }
+
+    pub fn user_id(&self, user_id: i32) -> i32 {
+        self.pseudonym("user", user_id)
+    }
+
+    pub fn user_name(&self, user_id: i32) -> String {
+        format!("Driver {}", self.user_id(user_id))
+    }
+
+    pub fn team_name(&self, team_id: i32) -> String {
+        format!("Team {}", self.pseudonym("team", team_id))
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, reader: &mut IbtReader, path: P) -> Result<(), Error> {
+        self.write(reader, BufWriter::new(File::create(path)?))
+    }
+
+    pub fn write<W: Write>(&self, reader: &mut IbtReader, mut out: W) -> Result<(), Error> {
+        let session_info_data = read_bytes_file(
+            &mut reader.file,
+            reader.header.sesion_info_offset as usize,
+            reader.header.sesion_info_length as usize,
+        )?;
+        let end = session_info_data
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(session_info_data.len());
+        let yaml = self.session_info(
+            &CP1252.decode(&session_info_data[..end]),
+            &reader.session_info.driver_info,
+        );
+        let session_info = CP1252.encode_lossy(&yaml, b'?').to_vec();
+
+        let header = reader.header.clone();
+        let disk_header = reader.disk_header.clone();
+        let vars = reader.vars.clone();
+        write_ibt(
+            &mut out,
+            &header,
+            &disk_header,
+            &vars,
+            &session_info,
+            reader.samples(),
+        )
+    }
+
+    // Rewrites the YAML line by line so everything that isn't personal stays
+    // exactly as iRacing wrote it.
+    pub fn session_info(&self, yaml: &str, driver_info: &DriverInfo) -> String {
+        let mut section = "";
+        let mut driver = None;
+        let mut lines: Vec<String> = vec![];
+        for line in yaml.lines() {
+            if !line.starts_with(' ') && line.ends_with(':') {
+                section = line.trim_end_matches(':');
+            }
+            let trimmed = line.trim_start();
+            if section == "DriverInfo" && trimmed.starts_with("- CarIdx:") {
+                driver = Some(driver.map_or(0, |index| index + 1));
+            }
+
+            let (key, value) = match trimmed.trim_start_matches("- ").split_once(':') {
+                Some((key, value)) if section == "DriverInfo" => (key, value.trim()),
+                _ => {
+                    lines.push(line.to_string());
+                    continue;
+                }
+            };
+            let replacement = match driver.and_then(|index| driver_info.drivers.get(index)) {
+                None if key == "DriverUserID" => {
+                    Some(self.user_id(driver_info.driver_user_id).to_string())
+                }
+                Some(driver) if driver.user_id > 0 => match key {
+                    "UserName" => Some(self.user_name(driver.user_id)),
+                    "AbbrevName" if !value.is_empty() => {
+                        Some(format!("{}, D", self.user_id(driver.user_id)))
+                    }
+                    "Initials" if !value.is_empty() => Some("D".to_string()),
+                    "UserID" => Some(self.user_id(driver.user_id).to_string()),
+                    // outside of team events the team is named after the driver
+                    "TeamName" if driver.team_id == 0 => Some(self.user_name(driver.user_id)),
+                    "TeamName" => Some(self.team_name(driver.team_id)),
+                    "IRating" => Some(I_RATING.to_string()),
+                    "LicLevel" => Some(LIC_LEVEL.to_string()),
+                    "LicSubLevel" => Some(LIC_SUB_LEVEL.to_string()),
+                    "LicString" => Some(LIC_STRING.to_string()),
+                    "LicColor" => Some(LIC_COLOR.to_string()),
+                    "CarDesignStr" | "HelmetDesignStr" | "SuitDesignStr" | "CarNumberDesignStr" => {
+                        Some(plain_design(value))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            match replacement {
+                Some(replacement) => {
+                    let prefix = &line[..line.len() - line.trim_start().len()];
+                    let item = if trimmed.starts_with("- ") { "- " } else { "" };
+                    lines.push(format!("{prefix}{item}{key}: {replacement}"));
+                }
+                None => lines.push(line.to_string()),
+            }
+        }
+        let mut yaml = lines.join("\n");
+        yaml.push('\n');
+        yaml
+    }
+
+    // FNV-1a of the salt and the id, as a 6 digit number.
+    fn pseudonym(&self, kind: &str, id: i32) -> i32 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self
+            .salt
+            .bytes()
+            .chain(kind.bytes())
+            .chain(id.to_le_bytes())
+        {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        (hash % 900_000) as i32 + 100_000
+    }
+}
+
+// Keeps the shape of a design string (pattern and colours) but makes every
+// driver's the same.
+fn plain_design(design: &str) -> String {
+    design
+        .split(',')
+        .map(|part| {
+            if part.len() == 6 && part.chars().all(|c| c.is_ascii_hexdigit()) {
+                "ffffff"
+            } else {
+                "0"
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_anonymize() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let anonymizer = Anonymizer::new("secret");
+        let mut data = vec![];
+        anonymizer.write(&mut reader, &mut data).unwrap();
+
+        let mut again = vec![];
+        anonymizer
+            .write(
+                &mut IbtReader::open("./test/fixtures/amg.ibt").unwrap(),
+                &mut again,
+            )
+            .unwrap();
+        assert_eq!(data, again);
+        assert!(!String::from_utf8_lossy(&data).contains("Martsenkov"));
+        assert!(!String::from_utf8_lossy(&data).contains("290307"));
+
+        let mut anonymized = IbtReader::try_new(Box::new(Cursor::new(data))).unwrap();
+        let driver_info = &anonymized.session_info.driver_info;
+        let pseudonym = anonymizer.user_id(290307);
+        assert_ne!(pseudonym, Anonymizer::new("other").user_id(290307));
+        assert_eq!(driver_info.driver_user_id, pseudonym);
+        let driver = &driver_info.drivers[0];
+        assert_eq!(driver.user_id, pseudonym);
+        assert_eq!(driver.user_name, format!("Driver {pseudonym}"));
+        assert_eq!(driver.team_name, driver.user_name);
+        assert_eq!(driver.abbrev_name, None);
+        assert_eq!(driver.i_rating, 0);
+        assert_eq!(driver.lic_string, "R 0.00");
+        assert_eq!(driver.car_design_str, "0,ffffff,ffffff,ffffff");
+        assert_eq!(driver.car_number_design_str, "0,0,ffffff,ffffff,ffffff");
+        assert_eq!(driver.car_screen_name, "Mercedes AMG GT4");
+        assert_eq!(
+            anonymized.session_info.weekend_info.track_name,
+            "spielberg gp"
+        );
+
+        assert_eq!(anonymized.disk_header.record_count, 3371);
+        assert_eq!(anonymized.samples().count(), 3371);
+        let rpm = anonymized.find_var("RPM".to_string()).unwrap();
+        let original = reader.samples().nth(1001).unwrap();
+        let sample = anonymized.samples().nth(1001).unwrap();
+        assert_eq!(sample.get_by_header(&rpm), original.get_by_header(&rpm));
+    }
+}
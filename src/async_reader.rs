@@ -0,0 +1,113 @@
+use std::io::SeekFrom;
+
+use futures_util::{stream, Stream};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+use crate::{
+    error::Error,
+    headers::{
+        DiskHeader, Header, VarHeader, DISK_HEADER_BYTES_SIZE, HEADER_BYTES_SIZE,
+        VAR_HEADER_BYTES_SIZE,
+    },
+    parse_session_info, parse_vars,
+    samples::Sample,
+    session_info::SessionInfo,
+};
+
+pub struct AsyncIbtReader<R> {
+    file: R,
+    pub header: Header,
+    pub disk_header: DiskHeader,
+    pub vars: Vec<VarHeader>,
+    pub session_info: SessionInfo,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncIbtReader<R> {
+    pub async fn new(mut file: R) -> Result<AsyncIbtReader<R>, Error> {
+        let file_len = file.seek(SeekFrom::End(0)).await?;
+        let header = Header::from(read_bytes(&mut file, 0, HEADER_BYTES_SIZE).await?);
+        header.validate(file_len)?;
+        let disk_header = DiskHeader::from(
+            read_bytes(&mut file, HEADER_BYTES_SIZE, DISK_HEADER_BYTES_SIZE).await?,
+        );
+        let session_info_data = read_bytes(
+            &mut file,
+            header.sesion_info_offset as usize,
+            header.sesion_info_length as usize,
+        )
+        .await?;
+        let session_info = parse_session_info(&session_info_data)?;
+
+        let vars_data = read_bytes(
+            &mut file,
+            header.var_header_offset as usize,
+            header.num_vars as usize * VAR_HEADER_BYTES_SIZE,
+        )
+        .await?;
+        let vars = parse_vars(&vars_data, header.num_vars);
+        for var in &vars {
+            var.validate(header.buf_len)?;
+        }
+
+        Ok(AsyncIbtReader {
+            file,
+            header,
+            disk_header,
+            vars,
+            session_info,
+        })
+    }
+
+    pub fn samples(&mut self) -> impl Stream<Item = Sample> + '_ {
+        let buf_offset = self.header.buf_offset as usize;
+        let length = self.header.buf_len as usize;
+        stream::unfold((&mut self.file, 0), move |(file, current)| async move {
+            let from = buf_offset + current * length;
+            match read_bytes(file, from, length).await {
+                Ok(data) => Some((Sample::from(data), (file, current + 1))),
+                Err(_) => None,
+            }
+        })
+    }
+
+    pub fn find_var(&self, name: String) -> Option<VarHeader> {
+        self.vars.iter().find(|var| var.name == name).cloned()
+    }
+}
+
+async fn read_bytes<R: AsyncRead + AsyncSeek + Unpin>(
+    file: &mut R,
+    from: usize,
+    size: usize,
+) -> std::io::Result<Vec<u8>> {
+    let mut buffer: Vec<u8> = vec![0; size];
+    file.seek(SeekFrom::Start(from as u64)).await?;
+    file.read_exact(&mut buffer).await?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::SampleValue;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_async_reader() {
+        let file = tokio::fs::File::open("./test/fixtures/amg.ibt")
+            .await
+            .unwrap();
+        let mut reader = AsyncIbtReader::new(file).await.unwrap();
+        assert_eq!(reader.header.tick_rate, 60);
+        assert_eq!(reader.vars.len(), 267);
+        assert_eq!(reader.session_info.weekend_info.track_name, "spielberg gp");
+
+        let rpm = reader.find_var("RPM".to_string()).unwrap();
+        let samples: Vec<Sample> = reader.samples().collect().await;
+        assert_eq!(samples.len(), 3371);
+        assert_eq!(
+            samples[1001].get_by_header(&rpm).unwrap(),
+            SampleValue::Float32(991.8974)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    catalogue::ibt_files,
+    error::Error,
+    samples::{Sample, SampleValue},
+    IbtReader,
+};
+
+pub struct BatchResult<T> {
+    pub path: PathBuf,
+    pub result: Result<T, Error>,
+}
+
+pub struct FileSummary {
+    pub track: String,
+    pub car: Option<String>,
+    pub driver: Option<String>,
+    pub session_types: Vec<String>,
+    pub samples: usize,
+    // seconds
+    pub duration: f64,
+}
+
+type ProgressCallback = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+// Processes many .ibt files in parallel. A file that can't be read doesn't
+// stop the others, its error is returned next to its path instead.
+pub struct Batch {
+    paths: Vec<PathBuf>,
+    progress: Option<ProgressCallback>,
+}
+
+impl Batch {
+    pub fn new(paths: Vec<PathBuf>) -> Batch {
+        Batch {
+            paths,
+            progress: None,
+        }
+    }
+
+    // All .ibt files in `dir` and its sub directories.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Batch, Error> {
+        Ok(Batch::new(ibt_files(dir)?))
+    }
+
+    // Called with the number of processed files and the total after every file.
+    pub fn on_progress<F: Fn(usize, usize) + Send + Sync + 'static>(mut self, f: F) -> Batch {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    pub fn run<T, F>(&self, f: F) -> Vec<BatchResult<T>>
+    where
+        T: Send,
+        F: Fn(&mut IbtReader) -> T + Send + Sync,
+    {
+        let done = AtomicUsize::new(0);
+        self.paths
+            .par_iter()
+            .map(|path| {
+                let result = IbtReader::open(path).map(|mut reader| f(&mut reader));
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(progress) = &self.progress {
+                    progress(done, self.paths.len());
+                }
+                BatchResult {
+                    path: path.clone(),
+                    result,
+                }
+            })
+            .collect()
+    }
+
+    pub fn summaries(&self) -> Vec<BatchResult<FileSummary>> {
+        self.run(summarize)
+    }
+}
+
+pub fn summarize(reader: &mut IbtReader) -> FileSummary {
+    let session_info = &reader.session_info;
+    let driver_info = &session_info.driver_info;
+    let driver = driver_info
+        .drivers
+        .iter()
+        .find(|driver| driver.car_idx == driver_info.driver_car_idx);
+    let samples = reader.disk_header.record_count as usize;
+
+    FileSummary {
+        track: session_info.weekend_info.track_display_name.clone(),
+        car: driver.map(|driver| driver.car_screen_name.clone()),
+        driver: driver.map(|driver| driver.user_name.clone()),
+        session_types: session_info
+            .session_info
+            .sessions
+            .iter()
+            .map(|session| session.session_type.clone())
+            .collect(),
+        samples,
+        duration: samples as f64 / reader.header.tick_rate as f64,
+    }
+}
+
+// Decodes channels of a single large file in parallel, every chunk of
+// samples is read through its own file handle.
+pub fn read_channels_parallel<P: AsRef<Path> + Sync>(
+    path: P,
+    names: &[&str],
+    chunks: usize,
+) -> Result<Vec<Vec<SampleValue>>, Error> {
+    let reader = IbtReader::open(&path)?;
+    let vars = names
+        .iter()
+        .map(|name| {
+            reader
+                .find_var(name.to_string())
+                .ok_or_else(|| Error::MissingVar(name.to_string()))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let buf_offset = reader.header.buf_offset as u64;
+    let length = reader.header.buf_len as usize;
+    let file_len = fs::metadata(&path)?.len();
+    let records = (file_len.saturating_sub(buf_offset) / length as u64) as usize;
+    let per_chunk = records.div_ceil(chunks.max(1)).max(1);
+
+    let decoded = (0..records)
+        .step_by(per_chunk)
+        .collect::<Vec<usize>>()
+        .par_iter()
+        .map(|start| {
+            let count = per_chunk.min(records - start);
+            let mut file = File::open(&path)?;
+            file.seek(SeekFrom::Start(buf_offset + (start * length) as u64))?;
+            let mut data = vec![0; count * length];
+            file.read_exact(&mut data)?;
+
+            let mut columns: Vec<Vec<SampleValue>> = vec![Vec::with_capacity(count); vars.len()];
+            for row in data.chunks_exact(length) {
+                let sample = Sample::from(row.to_vec());
+                for (var, column) in vars.iter().zip(columns.iter_mut()) {
+                    column.push(sample.get_by_header(var).unwrap());
+                }
+            }
+            Ok(columns)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut channels: Vec<Vec<SampleValue>> = vec![Vec::with_capacity(records); vars.len()];
+    for chunk in decoded {
+        for (channel, column) in channels.iter_mut().zip(chunk) {
+            channel.extend(column);
+        }
+    }
+    Ok(channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch() {
+        let progress = std::sync::Arc::new(AtomicUsize::new(0));
+        let calls = progress.clone();
+        let batch = Batch::new(vec![
+            PathBuf::from("./test/fixtures/amg.ibt"),
+            PathBuf::from("./test/fixtures/missing.ibt"),
+        ])
+        .on_progress(move |_, total| {
+            assert_eq!(total, 2);
+            calls.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let results = batch.summaries();
+        assert_eq!(progress.load(Ordering::Relaxed), 2);
+        let summary = results[0].result.as_ref().unwrap();
+        assert_eq!(summary.track, "Red Bull Ring");
+        assert_eq!(summary.car.as_deref(), Some("Mercedes AMG GT4"));
+        assert_eq!(summary.samples, 3371);
+        assert!(matches!(results[1].result, Err(Error::Io(_))));
+
+        let dir = Batch::from_dir("./test/fixtures").unwrap();
+        assert!(dir
+            .paths()
+            .contains(&PathBuf::from("./test/fixtures/amg.ibt")));
+    }
+
+    #[test]
+    fn test_batch_corrupt_files() {
+        let data = fs::read("./test/fixtures/amg.ibt").unwrap();
+        let dir = std::env::temp_dir().join("itelem_batch_corrupt");
+        fs::create_dir_all(&dir).unwrap();
+        // negative session info length, negative number of vars, a var that
+        // points past the end of the sample and a file cut short
+        let mut corrupt = vec![];
+        for (name, at, value) in [("info", 16, -1_i32), ("vars", 24, -1), ("var", 148, 5000)] {
+            let mut data = data.clone();
+            data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            corrupt.push((dir.join(format!("{name}.ibt")), data));
+        }
+        corrupt.push((dir.join("truncated.ibt"), data[..20000].to_vec()));
+        for (path, data) in &corrupt {
+            fs::write(path, data).unwrap();
+        }
+
+        let mut paths = vec![PathBuf::from("./test/fixtures/amg.ibt")];
+        paths.extend(corrupt.iter().map(|(path, _)| path.clone()));
+        let results = Batch::new(paths).run(|reader| reader.samples().count());
+        assert_eq!(*results[0].result.as_ref().unwrap(), 3371);
+        for result in &results[1..] {
+            assert!(
+                matches!(result.result, Err(Error::InvalidHeader(_))),
+                "{:?}",
+                result.path
+            );
+        }
+
+        assert!(matches!(
+            read_channels_parallel("./test/fixtures/amg.ibt", &["RPM", "Nope"], 2),
+            Err(Error::MissingVar(name)) if name == "Nope"
+        ));
+    }
+
+    #[test]
+    fn test_read_channels_parallel() {
+        let channels =
+            read_channels_parallel("./test/fixtures/amg.ibt", &["RPM", "Gear"], 4).unwrap();
+        assert_eq!(channels[0].len(), 3371);
+        assert_eq!(channels[0][1001], SampleValue::Float32(991.8974));
+
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let gear = reader.find_var("Gear".to_string()).unwrap();
+        let sequential: Vec<SampleValue> = reader
+            .samples()
+            .map(|sample| sample.get_by_header(&gear).unwrap())
+            .collect();
+        assert_eq!(channels[1], sequential);
+    }
+}
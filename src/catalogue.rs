@@ -0,0 +1,370 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Seek},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    export::DateTime,
+    get_var_header,
+    headers::Header,
+    parse_vars, read_bytes_file,
+    samples::{Sample, SampleValue},
+    IbtSummary,
+};
+
+// A searchable index of a folder of .ibt files, stored as JSON so it can be
+// reloaded and brought up to date without reading unchanged files again.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Catalogue {
+    pub entries: Vec<CatalogueEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogueEntry {
+    pub path: PathBuf,
+    // seconds since the unix epoch, together with the size used to notice
+    // files that changed since they were indexed
+    pub modified: u64,
+    pub size: u64,
+    pub track_name: String,
+    pub track_display_name: String,
+    pub track_config_name: String,
+    pub car: Option<String>,
+    pub driver: Option<String>,
+    pub series_id: i32,
+    pub session_types: Vec<String>,
+    pub start_date: i64,
+    // YYYY-MM-DD (UTC) of when the file was recorded
+    pub date: String,
+    // seconds
+    pub duration: f64,
+    pub best_lap: Option<f32>,
+}
+
+// Filters over the catalogue, text matches ignore case.
+#[derive(Default)]
+pub struct Query {
+    track: Option<String>,
+    config: Option<String>,
+    car: Option<String>,
+    driver: Option<String>,
+    series_id: Option<i32>,
+    session_type: Option<String>,
+    from_date: Option<String>,
+    to_date: Option<String>,
+    best_lap_under: Option<f32>,
+}
+
+impl Catalogue {
+    // An index that doesn't exist yet loads as an empty catalogue.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Catalogue, Error> {
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Catalogue::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+
+    // Brings the catalogue in line with the .ibt files under `dir`. Files
+    // with the same modification time and size as when they were indexed
+    // aren't read again and entries of deleted files are dropped. Files that
+    // can't be read are returned with their error.
+    pub fn update<P: AsRef<Path>>(&mut self, dir: P) -> Result<Vec<(PathBuf, Error)>, Error> {
+        let mut entries = Vec::new();
+        let mut failed = Vec::new();
+        for path in ibt_files(dir)? {
+            // the file can be gone by now if it was removed while listing
+            let (modified, size) = match modified_and_size(&path) {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    failed.push((path, err.into()));
+                    continue;
+                }
+            };
+
+            let existing = self.entries.iter().position(|entry| {
+                entry.path == path && entry.modified == modified && entry.size == size
+            });
+            if let Some(index) = existing {
+                entries.push(self.entries.swap_remove(index));
+                continue;
+            }
+
+            match CatalogueEntry::read(&path) {
+                Ok(mut entry) => {
+                    entry.modified = modified;
+                    entry.size = size;
+                    entries.push(entry);
+                }
+                Err(err) => failed.push((path, err)),
+            }
+        }
+        self.entries = entries;
+        Ok(failed)
+    }
+
+    pub fn query(&self, query: &Query) -> Vec<&CatalogueEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| query.matches(entry))
+            .collect()
+    }
+}
+
+impl CatalogueEntry {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<CatalogueEntry, Error> {
+        let mut file = File::open(&path)?;
+        let summary = IbtSummary::from_reader(&mut file)?;
+        let best_lap = best_lap(&mut file, &summary.header)?;
+        let date = DateTime::from_unix(summary.disk_header.start_date as f64);
+
+        Ok(CatalogueEntry {
+            path: path.as_ref().to_path_buf(),
+            modified: 0,
+            size: 0,
+            track_name: summary.track_name,
+            track_display_name: summary.track_display_name,
+            track_config_name: summary.track_config_name,
+            car: summary.car,
+            driver: summary.driver,
+            series_id: summary.series_id,
+            session_types: summary.session_types,
+            start_date: summary.disk_header.start_date,
+            date: format!("{:04}-{:02}-{:02}", date.year, date.month, date.day),
+            duration: summary.duration,
+            best_lap,
+        })
+    }
+}
+
+impl Query {
+    // Matches either the internal or the display name of the track.
+    pub fn track(mut self, track: &str) -> Query {
+        self.track = Some(track.to_lowercase());
+        self
+    }
+
+    pub fn config(mut self, config: &str) -> Query {
+        self.config = Some(config.to_lowercase());
+        self
+    }
+
+    pub fn car(mut self, car: &str) -> Query {
+        self.car = Some(car.to_lowercase());
+        self
+    }
+
+    pub fn driver(mut self, driver: &str) -> Query {
+        self.driver = Some(driver.to_lowercase());
+        self
+    }
+
+    pub fn series(mut self, series_id: i32) -> Query {
+        self.series_id = Some(series_id);
+        self
+    }
+
+    // Files containing at least one session of this type, e.g. "Race".
+    pub fn session_type(mut self, session_type: &str) -> Query {
+        self.session_type = Some(session_type.to_lowercase());
+        self
+    }
+
+    // Inclusive range of YYYY-MM-DD dates.
+    pub fn from_date(mut self, date: &str) -> Query {
+        self.from_date = Some(date.to_string());
+        self
+    }
+
+    pub fn to_date(mut self, date: &str) -> Query {
+        self.to_date = Some(date.to_string());
+        self
+    }
+
+    pub fn best_lap_under(mut self, seconds: f32) -> Query {
+        self.best_lap_under = Some(seconds);
+        self
+    }
+
+    fn matches(&self, entry: &CatalogueEntry) -> bool {
+        let eq = |filter: &Option<String>, value: Option<&str>| match filter {
+            Some(filter) => value.is_some_and(|value| value.to_lowercase() == *filter),
+            None => true,
+        };
+
+        (eq(&self.track, Some(&entry.track_name))
+            || eq(&self.track, Some(&entry.track_display_name)))
+            && eq(&self.config, Some(&entry.track_config_name))
+            && eq(&self.car, entry.car.as_deref())
+            && eq(&self.driver, entry.driver.as_deref())
+            && self.series_id.is_none_or(|id| id == entry.series_id)
+            && (self.session_type.is_none()
+                || entry
+                    .session_types
+                    .iter()
+                    .any(|session_type| eq(&self.session_type, Some(session_type))))
+            && self
+                .from_date
+                .as_ref()
+                .is_none_or(|from| entry.date >= *from)
+            && self.to_date.as_ref().is_none_or(|to| entry.date <= *to)
+            && self
+                .best_lap_under
+                .is_none_or(|max| entry.best_lap.is_some_and(|lap| lap < max))
+    }
+}
+
+fn modified_and_size(path: &Path) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    Ok((modified, metadata.len()))
+}
+
+// All .ibt files in `dir` and its sub directories.
+pub(crate) fn ibt_files<P: AsRef<Path>>(dir: P) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "ibt") {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+// `LapBestLapTime` of the last sample, read without going through the rest.
+fn best_lap<R: io::Read + Seek>(file: &mut R, header: &Header) -> Result<Option<f32>, Error> {
+    let vars = parse_vars(&get_var_header(file, header)?, header.num_vars);
+    let var = match vars.iter().find(|var| var.name == "LapBestLapTime") {
+        Some(var) => var,
+        None => return Ok(None),
+    };
+    var.validate(header.buf_len)?;
+
+    let length = header.buf_len as u64;
+    let records = file
+        .seek(io::SeekFrom::End(0))?
+        .saturating_sub(header.buf_offset as u64)
+        / length;
+    if records == 0 {
+        return Ok(None);
+    }
+    let offset = header.buf_offset as u64 + (records - 1) * length;
+    let sample = Sample::from(read_bytes_file(file, offset as usize, length as usize)?);
+    Ok(match sample.get_by_header(var) {
+        Some(SampleValue::Float32(lap)) if lap > 0.0 => Some(lap),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogue() {
+        let mut catalogue = Catalogue::default();
+        let failed = catalogue.update("./test/fixtures").unwrap();
+        let amg = catalogue
+            .entries
+            .iter()
+            .find(|entry| entry.path.ends_with("amg.ibt"))
+            .unwrap()
+            .clone();
+        assert!(failed.is_empty());
+        assert_eq!(amg.track_display_name, "Red Bull Ring");
+        assert_eq!(amg.date, "2023-04-10");
+        assert_eq!(amg.best_lap, None);
+
+        let query = Query::default()
+            .track("Spielberg GP")
+            .config("grand prix")
+            .car("Mercedes AMG GT4")
+            .driver("georgi martsenkov")
+            .session_type("offline testing")
+            .from_date("2023-04-01")
+            .to_date("2023-04-30");
+        assert_eq!(catalogue.query(&query), vec![&amg]);
+        assert!(catalogue
+            .query(&Query::default().car("Porsche 911 GT3 R"))
+            .is_empty());
+        assert!(catalogue
+            .query(&Query::default().best_lap_under(100.0))
+            .is_empty());
+
+        let path = std::env::temp_dir().join("itelem_catalogue.json");
+        catalogue.save(&path).unwrap();
+        let mut loaded = Catalogue::load(&path).unwrap();
+        assert!(loaded.update("./test/fixtures").unwrap().is_empty());
+        assert_eq!(loaded.entries, catalogue.entries);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_catalogue_unreadable_file() {
+        let dir = std::env::temp_dir().join("itelem_catalogue_unreadable");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("./test/fixtures/amg.ibt", dir.join("amg.ibt")).unwrap();
+        // listed, but gone by the time it's looked at
+        std::os::unix::fs::symlink(dir.join("removed"), dir.join("gone.ibt")).unwrap();
+
+        let mut catalogue = Catalogue::default();
+        let failed = catalogue.update(&dir).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, dir.join("gone.ibt"));
+        assert!(matches!(failed[0].1, Error::Io(_)));
+        assert_eq!(catalogue.entries.len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_catalogue_corrupt_best_lap() {
+        let dir = std::env::temp_dir().join("itelem_catalogue_corrupt");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy("./test/fixtures/amg.ibt", dir.join("amg.ibt")).unwrap();
+
+        let mut data = fs::read("./test/fixtures/amg.ibt").unwrap();
+        let reader = crate::IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let index = reader
+            .vars
+            .iter()
+            .position(|var| var.name == "LapBestLapTime")
+            .unwrap();
+        let offset = reader.header.var_header_offset as usize
+            + index * crate::headers::VAR_HEADER_BYTES_SIZE
+            + 4;
+        data[offset..offset + 4].copy_from_slice(&5000_i32.to_le_bytes());
+        fs::write(dir.join("corrupt.ibt"), data).unwrap();
+
+        let mut catalogue = Catalogue::default();
+        let failed = catalogue.update(&dir).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, dir.join("corrupt.ibt"));
+        assert!(matches!(failed[0].1, Error::InvalidHeader(_)));
+        assert_eq!(catalogue.entries.len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
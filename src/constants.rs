@@ -1,5 +1,5 @@
 #[repr(usize)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Flags {
     Checkered = 0x00000001,
     White = 0x00000002,
@@ -32,6 +32,41 @@ pub enum Flags {
     StartGo = 0x80000000,
 }
 
+impl Flags {
+    pub fn from_bits(bits: u32) -> Vec<Flags> {
+        [
+            Flags::Checkered,
+            Flags::White,
+            Flags::Green,
+            Flags::Yellow,
+            Flags::Red,
+            Flags::Blue,
+            Flags::Debris,
+            Flags::Crossed,
+            Flags::YellowWaving,
+            Flags::OneLapToGreen,
+            Flags::GreenHeld,
+            Flags::TenToGo,
+            Flags::FiveToGo,
+            Flags::RandomWaving,
+            Flags::Caution,
+            Flags::CautionWaving,
+            Flags::Black,
+            Flags::Disqualify,
+            Flags::Servicible,
+            Flags::Furled,
+            Flags::Repair,
+            Flags::StartHidden,
+            Flags::StartReady,
+            Flags::StartSet,
+            Flags::StartGo,
+        ]
+        .into_iter()
+        .filter(|flag| bits as usize & *flag as usize != 0)
+        .collect()
+    }
+}
+
 #[derive(Debug)]
 pub enum EngineWarnings {
     WaterTempWarning = 0x01,
@@ -43,7 +78,7 @@ pub enum EngineWarnings {
     OilTempWarning = 0x40,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TrackLocation {
     NotInWorld = -1,
     OffTrack = 0,
@@ -52,6 +87,21 @@ pub enum TrackLocation {
     OnTrack = 3,
 }
 
+impl TryFrom<i32> for TrackLocation {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<TrackLocation, i32> {
+        match value {
+            -1 => Ok(TrackLocation::NotInWorld),
+            0 => Ok(TrackLocation::OffTrack),
+            1 => Ok(TrackLocation::InPitStall),
+            2 => Ok(TrackLocation::AproachingPits),
+            3 => Ok(TrackLocation::OnTrack),
+            _ => Err(value),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum TrackSurface {
     SurfaceNotInWorld = -1,
@@ -124,7 +174,7 @@ pub enum CameraState {
     UseMouseAimMode = 0x0100,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PitFlags {
     LFTireChange = 0x0001,
     RFTireChange = 0x0002,
@@ -136,6 +186,23 @@ pub enum PitFlags {
     FastRepair = 0x0040,
 }
 
+impl PitFlags {
+    pub fn from_bits(bits: u32) -> Vec<PitFlags> {
+        [
+            PitFlags::LFTireChange,
+            PitFlags::RFTireChange,
+            PitFlags::LRTireChange,
+            PitFlags::RRTireChange,
+            PitFlags::FuelFill,
+            PitFlags::WindshieldTearoff,
+            PitFlags::FastRepair,
+        ]
+        .into_iter()
+        .filter(|flag| bits & *flag as u32 != 0)
+        .collect()
+    }
+}
+
 #[derive(Debug)]
 pub enum PitStatus {
     PitSvNone = 0,
@@ -0,0 +1,63 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    SessionInfo(serde_yaml::Error),
+    Catalogue(serde_json::Error),
+    NoFiles,
+    // Nothing in the file matched the range asked for.
+    NoSamples,
+    MissingVar(String),
+    // Offsets or lengths in the headers that can't be right for the file.
+    InvalidHeader(String),
+    // The stream reader needs the var headers, session info and samples to
+    // come in increasing offset order.
+    UnorderedLayout,
+    SessionMismatch {
+        session_id: i32,
+        sub_session_id: i32,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {err}"),
+            Error::SessionInfo(err) => write!(f, "invalid session info: {err}"),
+            Error::Catalogue(err) => write!(f, "invalid catalogue: {err}"),
+            Error::NoFiles => write!(f, "no telemetry files given"),
+            Error::NoSamples => write!(f, "no samples in the given range"),
+            Error::MissingVar(name) => write!(f, "{name} isn't recorded in the file"),
+            Error::InvalidHeader(reason) => write!(f, "invalid header: {reason}"),
+            Error::UnorderedLayout => write!(f, "file can't be read without seeking"),
+            Error::SessionMismatch {
+                session_id,
+                sub_session_id,
+            } => write!(
+                f,
+                "file belongs to session {session_id} (sub session {sub_session_id}) of another event"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Error {
+        Error::SessionInfo(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Catalogue(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use crate::{
+    error::Error,
+    headers::VarHeader,
+    samples::{Sample, SampleValue, Samples},
+    session_info::Session,
+    IbtReader,
+};
+
+// A single event (practice, qualifying, race...) recorded across several .ibt
+// files, ordered by the time the files were started.
+pub struct Event {
+    pub files: Vec<IbtReader>,
+}
+
+pub struct EventSample<'a> {
+    pub file_index: usize,
+    pub sample: Sample,
+    vars: &'a [VarHeader],
+}
+
+impl EventSample<'_> {
+    pub fn get(&self, name: &str) -> Option<SampleValue> {
+        let var = self.vars.iter().find(|var| var.name == name)?;
+        self.sample.get_by_header(var)
+    }
+}
+
+impl Event {
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> Result<Event, Error> {
+        let files = paths
+            .iter()
+            .map(IbtReader::open)
+            .collect::<Result<Vec<IbtReader>, Error>>()?;
+        Event::new(files)
+    }
+
+    pub fn new(mut files: Vec<IbtReader>) -> Result<Event, Error> {
+        let first = &files
+            .first()
+            .ok_or(Error::NoFiles)?
+            .session_info
+            .weekend_info;
+        let (session_id, sub_session_id) = (first.session_id, first.sub_session_id);
+
+        let mismatch = files.iter().find(|file| {
+            let weekend_info = &file.session_info.weekend_info;
+            weekend_info.session_id != session_id || weekend_info.sub_session_id != sub_session_id
+        });
+        if let Some(file) = mismatch {
+            return Err(Error::SessionMismatch {
+                session_id: file.session_info.weekend_info.session_id,
+                sub_session_id: file.session_info.weekend_info.sub_session_id,
+            });
+        }
+
+        files.sort_by(|a, b| {
+            a.disk_header
+                .start_date
+                .cmp(&b.disk_header.start_date)
+                .then(
+                    a.disk_header
+                        .start_time
+                        .total_cmp(&b.disk_header.start_time),
+                )
+        });
+        Ok(Event { files })
+    }
+
+    // Sessions from all files, keyed by `session_num`. Later files carry the
+    // most up to date results so they take precedence.
+    pub fn sessions(&self) -> Vec<&Session> {
+        let mut sessions: Vec<&Session> = Vec::new();
+        for file in &self.files {
+            for session in &file.session_info.session_info.sessions {
+                match sessions
+                    .iter_mut()
+                    .find(|existing| existing.session_num == session.session_num)
+                {
+                    Some(existing) => *existing = session,
+                    None => sessions.push(session),
+                }
+            }
+        }
+        sessions.sort_by_key(|session| session.session_num);
+        sessions
+    }
+
+    pub fn timeline(&mut self) -> impl Iterator<Item = EventSample<'_>> {
+        self.files
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(file_index, reader)| {
+                let vars = &reader.vars;
+                let samples = Samples {
+                    current: 0,
+                    buf_offset: reader.header.buf_offset,
+                    length: reader.header.buf_len,
+                    file: &mut reader.file,
+                };
+                samples.map(move |sample| EventSample {
+                    file_index,
+                    sample,
+                    vars,
+                })
+            })
+    }
+
+    pub fn session(&mut self, session_num: i32) -> impl Iterator<Item = EventSample<'_>> {
+        self.timeline().filter(move |sample| {
+            sample.get("SessionNum").map(|num| num.int()) == Some(session_num)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event() {
+        let mut event = Event::open(&["./test/fixtures/amg.ibt"]).unwrap();
+        assert_eq!(event.files.len(), 1);
+
+        let sessions = event.sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_name, "TESTING");
+
+        assert_eq!(event.timeline().count(), 3371);
+        assert_eq!(event.session(0).count(), 3371);
+        assert_eq!(event.session(1).count(), 0);
+
+        let first = event.timeline().next().unwrap();
+        assert_eq!(first.file_index, 0);
+        assert_eq!(
+            first.get("SessionTime"),
+            Some(SampleValue::Float64(54.049999745686165))
+        );
+
+        assert!(matches!(Event::new(vec![]), Err(Error::NoFiles)));
+    }
+}
@@ -0,0 +1,210 @@
+use serde_json::json;
+
+use crate::{export::DateTime, IbtReader, SessionSamples};
+
+pub struct GpsPoint {
+    pub session_time: f64,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f32,
+    pub speed: Option<f32>,
+    pub throttle: Option<f32>,
+    pub brake: Option<f32>,
+}
+
+pub struct GpsLap {
+    pub lap: i32,
+    pub points: Vec<GpsPoint>,
+}
+
+pub struct GpsTrack {
+    // unix time of the first sample, `session_start_time` is its SessionTime
+    pub start_date: i64,
+    pub session_start_time: f64,
+    pub laps: Vec<GpsLap>,
+}
+
+impl GpsTrack {
+    pub fn from_reader(reader: &mut IbtReader, session: &SessionSamples) -> Option<GpsTrack> {
+        let session_time = reader.find_var("SessionTime".to_string())?;
+        let lap = reader.find_var("Lap".to_string())?;
+        let lat = reader.find_var("Lat".to_string())?;
+        let lon = reader.find_var("Lon".to_string())?;
+        let alt = reader.find_var("Alt".to_string())?;
+        let speed = reader.find_var("Speed".to_string());
+        let throttle = reader.find_var("Throttle".to_string());
+        let brake = reader.find_var("Brake".to_string());
+
+        let mut track = GpsTrack {
+            start_date: reader.disk_header.start_date,
+            session_start_time: reader.disk_header.start_time,
+            laps: vec![],
+        };
+
+        for sample in reader.session_samples(session) {
+            let point = GpsPoint {
+                session_time: sample.get_by_header(&session_time).unwrap().float_64(),
+                lat: sample.get_by_header(&lat).unwrap().float_64(),
+                lon: sample.get_by_header(&lon).unwrap().float_64(),
+                alt: sample.get_by_header(&alt).unwrap().float_32(),
+                speed: speed
+                    .as_ref()
+                    .map(|var| sample.get_by_header(var).unwrap().float_32()),
+                throttle: throttle
+                    .as_ref()
+                    .map(|var| sample.get_by_header(var).unwrap().float_32()),
+                brake: brake
+                    .as_ref()
+                    .map(|var| sample.get_by_header(var).unwrap().float_32()),
+            };
+            // the car isn't in the world, e.g. while in the garage
+            if point.lat == 0.0 && point.lon == 0.0 {
+                continue;
+            }
+
+            let lap_num = sample.get_by_header(&lap).unwrap().int();
+            match track.laps.last_mut() {
+                Some(last) if last.lap == lap_num => last.points.push(point),
+                _ => track.laps.push(GpsLap {
+                    lap: lap_num,
+                    points: vec![point],
+                }),
+            }
+        }
+
+        Some(track)
+    }
+
+    pub fn timestamp(&self, session_time: f64) -> String {
+        format_timestamp(self.start_date as f64 + session_time - self.session_start_time)
+    }
+
+    pub fn to_gpx(&self) -> String {
+        let mut gpx = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<gpx version=\"1.1\" creator=\"itelem\" ",
+            "xmlns=\"http://www.topografix.com/GPX/1/1\" ",
+            "xmlns:itelem=\"https://github.com/gmartsenkov/itelem\">\n",
+        ));
+        for lap in &self.laps {
+            gpx.push_str(&format!(
+                "  <trk>\n    <name>Lap {}</name>\n    <trkseg>\n",
+                lap.lap
+            ));
+            for point in &lap.points {
+                gpx.push_str(&format!(
+                    "      <trkpt lat=\"{:.8}\" lon=\"{:.8}\"><ele>{:.2}</ele><time>{}</time>",
+                    point.lat,
+                    point.lon,
+                    point.alt,
+                    self.timestamp(point.session_time)
+                ));
+                let extensions: Vec<String> = [
+                    ("speed", point.speed),
+                    ("throttle", point.throttle),
+                    ("brake", point.brake),
+                ]
+                .iter()
+                .filter_map(|(name, value)| {
+                    value.map(|value| format!("<itelem:{name}>{value:.3}</itelem:{name}>"))
+                })
+                .collect();
+                if !extensions.is_empty() {
+                    gpx.push_str(&format!("<extensions>{}</extensions>", extensions.concat()));
+                }
+                gpx.push_str("</trkpt>\n");
+            }
+            gpx.push_str("    </trkseg>\n  </trk>\n");
+        }
+        gpx.push_str("</gpx>\n");
+        gpx
+    }
+
+    // One LineString per lap, per point values are stored in the
+    // `coordinateProperties` property like most mapping tools expect.
+    pub fn to_geojson(&self) -> String {
+        let features: Vec<serde_json::Value> = self
+            .laps
+            .iter()
+            .map(|lap| {
+                let coordinates: Vec<[f64; 3]> = lap
+                    .points
+                    .iter()
+                    .map(|point| [point.lon, point.lat, point.alt as f64])
+                    .collect();
+                let times: Vec<String> = lap
+                    .points
+                    .iter()
+                    .map(|point| self.timestamp(point.session_time))
+                    .collect();
+                let speed: Vec<Option<f32>> = lap.points.iter().map(|point| point.speed).collect();
+                let throttle: Vec<Option<f32>> =
+                    lap.points.iter().map(|point| point.throttle).collect();
+                let brake: Vec<Option<f32>> = lap.points.iter().map(|point| point.brake).collect();
+                json!({
+                    "type": "Feature",
+                    "properties": {
+                        "lap": lap.lap,
+                        "coordinateProperties": {
+                            "times": times,
+                            "speed": speed,
+                            "throttle": throttle,
+                            "brake": brake,
+                        },
+                    },
+                    "geometry": { "type": "LineString", "coordinates": coordinates },
+                })
+            })
+            .collect();
+
+        json!({ "type": "FeatureCollection", "features": features }).to_string()
+    }
+}
+
+// RFC 3339 UTC timestamp with millisecond precision from unix seconds.
+fn format_timestamp(seconds: f64) -> String {
+    let time = DateTime::from_unix(seconds);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time.year, time.month, time.day, time.hour, time.minute, time.second, time.millis
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_gps_export() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let session = reader.sessions().remove(0);
+        let track = GpsTrack::from_reader(&mut reader, &session).unwrap();
+
+        assert_eq!(track.laps.len(), 1);
+        assert_eq!(track.laps[0].points.len(), 3371);
+        assert_eq!(
+            track.timestamp(track.laps[0].points[0].session_time),
+            "2023-04-10T07:50:45.000Z"
+        );
+
+        let gpx = track.to_gpx();
+        assert!(gpx.contains("<name>Lap 0</name>"));
+        assert!(gpx.contains("<time>2023-04-10T07:50:45.000Z</time>"));
+
+        let geojson: serde_json::Value = serde_json::from_str(&track.to_geojson()).unwrap();
+        let feature = &geojson["features"][0];
+        assert_eq!(
+            feature["geometry"]["coordinates"].as_array().unwrap().len(),
+            3371
+        );
+        assert_eq!(
+            feature["properties"]["coordinateProperties"]["speed"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3371
+        );
+    }
+}
@@ -0,0 +1,41 @@
+pub mod gps;
+pub mod motec;
+
+pub(crate) struct DateTime {
+    pub year: i64,
+    pub month: i64,
+    pub day: i64,
+    pub hour: i64,
+    pub minute: i64,
+    pub second: i64,
+    pub millis: i64,
+}
+
+impl DateTime {
+    // UTC date and time from unix seconds, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn from_unix(seconds: f64) -> DateTime {
+        let millis = (seconds * 1000.0).round() as i64;
+        let days = millis.div_euclid(86_400_000);
+        let day_millis = millis.rem_euclid(86_400_000);
+
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+        DateTime {
+            year: yoe + era * 400 + (month <= 2) as i64,
+            month,
+            day,
+            hour: day_millis / 3_600_000,
+            minute: day_millis / 60_000 % 60,
+            second: day_millis / 1000 % 60,
+            millis: day_millis % 1000,
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::io::{self, Write};
+
+use crate::{export::DateTime, samples::SampleValue, IbtReader, SessionSamples};
+
+// Layout of the MoTeC i2 .ld format, the blocks are written back to back in
+// this order followed by the channel headers and then the channel data.
+const HEADER_SIZE: usize = 1762;
+const EVENT_SIZE: usize = 1154;
+const VENUE_SIZE: usize = 1100;
+const VEHICLE_SIZE: usize = 260;
+const CHANNEL_SIZE: usize = 124;
+
+const EVENT_PTR: usize = HEADER_SIZE;
+const VENUE_PTR: usize = EVENT_PTR + EVENT_SIZE;
+const VEHICLE_PTR: usize = VENUE_PTR + VENUE_SIZE;
+const CHANNELS_PTR: usize = VEHICLE_PTR + VEHICLE_SIZE;
+
+pub enum ChannelData {
+    Float(Vec<f32>),
+    Int(Vec<i32>),
+}
+
+pub struct MotecChannel {
+    pub name: String,
+    pub unit: String,
+    pub frequency: u16,
+    pub data: ChannelData,
+}
+
+pub struct MotecLog {
+    pub driver: String,
+    pub vehicle: String,
+    pub venue: String,
+    pub event: String,
+    pub session: String,
+    pub start_date: i64,
+    pub channels: Vec<MotecChannel>,
+    // seconds since the start of the log at which each lap was started
+    pub beacons: Vec<f64>,
+}
+
+impl MotecLog {
+    // Every variable becomes a channel. High frequency variables are exported
+    // at their sub-tick rate, for other arrays only the first element is kept.
+    // A log holds one session, so beacons follow a single run of laps.
+    pub fn from_reader(reader: &mut IbtReader, session: &SessionSamples) -> MotecLog {
+        let session_info = &reader.session_info;
+        let driver_info = &session_info.driver_info;
+        let driver = driver_info
+            .drivers
+            .iter()
+            .find(|driver| driver.car_idx == driver_info.driver_car_idx);
+        let weekend_info = &session_info.weekend_info;
+
+        let mut log = MotecLog {
+            driver: driver
+                .map(|driver| driver.user_name.clone())
+                .unwrap_or_default(),
+            vehicle: driver
+                .map(|driver| driver.car_screen_name.clone())
+                .unwrap_or_default(),
+            venue: format!(
+                "{} {}",
+                weekend_info.track_display_name, weekend_info.track_config_name
+            ),
+            event: weekend_info.event_type.clone(),
+            session: session
+                .session
+                .as_ref()
+                .map(|session| session.session_type.clone())
+                .unwrap_or_default(),
+            start_date: reader.disk_header.start_date,
+            channels: reader
+                .vars
+                .iter()
+                .map(|var| MotecChannel {
+                    name: var.name.clone(),
+                    unit: var.unit.clone(),
+                    frequency: match var.is_time_series() {
+                        true => (reader.header.tick_rate * var.count) as u16,
+                        false => reader.header.tick_rate as u16,
+                    },
+                    data: match var.r#type {
+                        4 | 5 => ChannelData::Float(vec![]),
+                        _ => ChannelData::Int(vec![]),
+                    },
+                })
+                .collect(),
+            beacons: vec![],
+        };
+
+        let vars = reader.vars.clone();
+        let session_time = reader.find_var("SessionTime".to_string());
+        let lap = reader.find_var("Lap".to_string());
+        let mut start_time: Option<f64> = None;
+        let mut current_lap: Option<i32> = None;
+
+        for sample in reader.session_samples(session) {
+            for (var, channel) in vars.iter().zip(log.channels.iter_mut()) {
+                let values = match var.is_time_series() {
+                    true => sample.get_all_by_header(var),
+                    false => vec![sample.get_by_header(var).unwrap()],
+                };
+                for value in values {
+                    match &mut channel.data {
+                        ChannelData::Float(data) => data.push(value.as_f64() as f32),
+                        ChannelData::Int(data) => data.push(match value {
+                            // keep every bit, `as_f64` would saturate flags
+                            // with the top bit set
+                            SampleValue::BitField(bits) => bits as i32,
+                            value => value.as_f64() as i32,
+                        }),
+                    }
+                }
+            }
+
+            if let (Some(session_time), Some(lap)) = (&session_time, &lap) {
+                let time = sample.get_by_header(session_time).unwrap().float_64();
+                let lap_num = sample.get_by_header(lap).unwrap().int();
+                let start = *start_time.get_or_insert(time);
+                if current_lap.is_some_and(|current| lap_num > current) {
+                    log.beacons.push(time - start);
+                }
+                current_lap = Some(lap_num);
+            }
+        }
+
+        log
+    }
+
+    pub fn write_ld<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let num_channels = self.channels.len();
+        let data_ptr = CHANNELS_PTR + num_channels * CHANNEL_SIZE;
+        let date = DateTime::from_unix(self.start_date as f64);
+
+        let mut header = vec![0; HEADER_SIZE];
+        put(&mut header, 0, &0x40_u32.to_le_bytes());
+        put(&mut header, 8, &(CHANNELS_PTR as u32).to_le_bytes());
+        put(&mut header, 12, &(data_ptr as u32).to_le_bytes());
+        put(&mut header, 36, &(EVENT_PTR as u32).to_le_bytes());
+        put(&mut header, 64, &1_u16.to_le_bytes());
+        put(&mut header, 66, &0x4240_u16.to_le_bytes());
+        put(&mut header, 68, &0xf_u16.to_le_bytes());
+        put(&mut header, 70, &0x1f44_u32.to_le_bytes());
+        put_str(&mut header, 74, 8, "ADL");
+        put(&mut header, 82, &420_u16.to_le_bytes());
+        put(&mut header, 84, &0xadb0_u16.to_le_bytes());
+        put(&mut header, 86, &(num_channels as u32).to_le_bytes());
+        let day = format!("{:02}/{:02}/{:04}", date.day, date.month, date.year);
+        put_str(&mut header, 94, 16, &day);
+        let time = format!("{:02}:{:02}:{:02}", date.hour, date.minute, date.second);
+        put_str(&mut header, 126, 16, &time);
+        put_str(&mut header, 158, 64, &self.driver);
+        put_str(&mut header, 222, 64, &self.vehicle);
+        put_str(&mut header, 350, 64, &self.venue);
+        put(&mut header, 1502, &0xc81a4_u32.to_le_bytes());
+        out.write_all(&header)?;
+
+        let mut event = vec![0; EVENT_SIZE];
+        put_str(&mut event, 0, 64, &self.event);
+        put_str(&mut event, 64, 64, &self.session);
+        put(&mut event, 1152, &(VENUE_PTR as u16).to_le_bytes());
+        out.write_all(&event)?;
+
+        let mut venue = vec![0; VENUE_SIZE];
+        put_str(&mut venue, 0, 64, &self.venue);
+        put(&mut venue, 1098, &(VEHICLE_PTR as u16).to_le_bytes());
+        out.write_all(&venue)?;
+
+        let mut vehicle = vec![0; VEHICLE_SIZE];
+        put_str(&mut vehicle, 0, 64, &self.vehicle);
+        out.write_all(&vehicle)?;
+
+        let mut channel_data_ptr = data_ptr;
+        for (index, channel) in self.channels.iter().enumerate() {
+            let meta_ptr = CHANNELS_PTR + index * CHANNEL_SIZE;
+            let prev = if index == 0 {
+                0
+            } else {
+                meta_ptr - CHANNEL_SIZE
+            };
+            let next = if index + 1 == num_channels {
+                0
+            } else {
+                meta_ptr + CHANNEL_SIZE
+            };
+            let (kind, len) = match &channel.data {
+                ChannelData::Float(data) => (0x07_u16, data.len()),
+                ChannelData::Int(data) => (0x05_u16, data.len()),
+            };
+
+            let mut meta = vec![0; CHANNEL_SIZE];
+            put(&mut meta, 0, &(prev as u32).to_le_bytes());
+            put(&mut meta, 4, &(next as u32).to_le_bytes());
+            put(&mut meta, 8, &(channel_data_ptr as u32).to_le_bytes());
+            put(&mut meta, 12, &(len as u32).to_le_bytes());
+            put(&mut meta, 16, &(0x2ee1 + index as u16).to_le_bytes());
+            put(&mut meta, 18, &kind.to_le_bytes());
+            put(&mut meta, 20, &4_u16.to_le_bytes());
+            put(&mut meta, 22, &channel.frequency.to_le_bytes());
+            // shift, multiplier, scale and decimal places, values are stored as is
+            put(&mut meta, 24, &0_i16.to_le_bytes());
+            put(&mut meta, 26, &1_i16.to_le_bytes());
+            put(&mut meta, 28, &1_i16.to_le_bytes());
+            put(&mut meta, 30, &0_i16.to_le_bytes());
+            put_str(&mut meta, 32, 32, &channel.name);
+            put_str(&mut meta, 64, 8, &channel.name);
+            put_str(&mut meta, 72, 12, &channel.unit);
+            out.write_all(&meta)?;
+
+            channel_data_ptr += len * 4;
+        }
+
+        for channel in &self.channels {
+            let data: Vec<u8> = match &channel.data {
+                ChannelData::Float(data) => data.iter().flat_map(|v| v.to_le_bytes()).collect(),
+                ChannelData::Int(data) => data.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            };
+            out.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+
+    // The .ldx file sits next to the .ld one and holds the lap markers.
+    pub fn write_ldx<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "<?xml version=\"1.0\"?>")?;
+        writeln!(
+            out,
+            "<LDXFile Locale=\"English_United Kingdom.1252\" DefaultLocale=\"C\" Version=\"1.6\">"
+        )?;
+        writeln!(out, " <Layers>\n  <Layer>\n   <MarkerBlock>")?;
+        writeln!(out, "    <MarkerGroup Name=\"Beacons\" Index=\"3\">")?;
+        for (index, time) in self.beacons.iter().enumerate() {
+            writeln!(
+                out,
+                "     <Marker Version=\"100\" ClassName=\"BCN\" Name=\"Manual.{}\" Flags=\"77\" Time=\"{:.0}\"/>",
+                index + 1,
+                time * 1e6
+            )?;
+        }
+        writeln!(
+            out,
+            "    </MarkerGroup>\n   </MarkerBlock>\n   <RangeBlock/>\n  </Layer>"
+        )?;
+        writeln!(out, "  <Details>")?;
+        writeln!(
+            out,
+            "   <String Id=\"Total Laps\" Value=\"{}\"/>",
+            self.beacons.len() + 1
+        )?;
+        writeln!(out, "  </Details>\n </Layers>\n</LDXFile>")?;
+        Ok(())
+    }
+}
+
+fn put(buffer: &mut [u8], offset: usize, bytes: &[u8]) {
+    buffer[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+// Strings are fixed size and zero padded, leave at least one terminating zero.
+fn put_str(buffer: &mut [u8], offset: usize, size: usize, value: &str) {
+    let bytes = value.as_bytes();
+    let len = bytes.len().min(size - 1);
+    put(buffer, offset, &bytes[..len]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_motec_export() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let session = reader.sessions().remove(0);
+        let log = MotecLog::from_reader(&mut reader, &session);
+        assert_eq!(log.channels.len(), 267);
+        assert_eq!(log.venue, "Red Bull Ring Grand Prix");
+        assert_eq!(log.session, "Offline Testing");
+        assert!(log.beacons.is_empty());
+
+        let mut ld = vec![];
+        log.write_ld(&mut ld).unwrap();
+        assert_eq!(read_u32(&ld, 0), 0x40);
+        assert_eq!(read_u32(&ld, 86), 267);
+        assert_eq!(&ld[94..104], b"10/04/2023");
+        // SteeringWheelTorque_ST has 6 sub-ticks per sample
+        assert_eq!(
+            ld.len(),
+            CHANNELS_PTR + 267 * CHANNEL_SIZE + (266 + 6) * 3371 * 4
+        );
+        let torque = log
+            .channels
+            .iter()
+            .find(|c| c.name == "SteeringWheelTorque_ST")
+            .unwrap();
+        assert_eq!(torque.frequency, 360);
+
+        let rpm_index = log.channels.iter().position(|c| c.name == "RPM").unwrap();
+        let rpm_meta = CHANNELS_PTR + rpm_index * CHANNEL_SIZE;
+        assert_eq!(&ld[rpm_meta + 32..rpm_meta + 35], b"RPM");
+        let rpm_data = read_u32(&ld, rpm_meta + 8) as usize + 1001 * 4;
+        let rpm = f32::from_le_bytes(ld[rpm_data..rpm_data + 4].try_into().unwrap());
+        assert_eq!(rpm, 991.8974);
+
+        let mut ldx = vec![];
+        log.write_ldx(&mut ldx).unwrap();
+        assert!(String::from_utf8(ldx).unwrap().contains("Value=\"1\""));
+    }
+
+    #[test]
+    fn test_motec_bitfields() {
+        // The first sample's `SessionFlags` with `StartGo`, the top bit, set.
+        let reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let flags = reader.find_var("SessionFlags".to_string()).unwrap();
+        let offset = (reader.header.buf_offset + flags.offset) as usize;
+        let mut data = std::fs::read("./test/fixtures/amg.ibt").unwrap();
+        data[offset..offset + 4].copy_from_slice(&0x8000_0004_u32.to_le_bytes());
+
+        let mut reader = IbtReader::try_new(Box::new(std::io::Cursor::new(data))).unwrap();
+        let session = reader.sessions().remove(0);
+        let log = MotecLog::from_reader(&mut reader, &session);
+        let channel = log
+            .channels
+            .iter()
+            .find(|c| c.name == "SessionFlags")
+            .unwrap();
+        match &channel.data {
+            ChannelData::Int(values) => assert_eq!(values[0] as u32, 0x8000_0004),
+            ChannelData::Float(_) => panic!("SessionFlags should be an int channel"),
+        }
+    }
+}
@@ -1,17 +1,22 @@
 use yore::code_pages::CP1252;
 
+use crate::error::Error;
+
 pub const HEADER_BYTES_SIZE: usize = 112;
 pub const DISK_HEADER_BYTES_SIZE: usize = 32;
 pub const VAR_HEADER_BYTES_SIZE: usize = 144;
+const MAX_BUFS: i32 = 4;
 
+#[derive(Clone)]
 pub struct DiskHeader {
-    pub start_date: f32,
+    pub start_date: i64,
     pub start_time: f64,
     pub end_time: f64,
     pub lap_count: i32,
     pub record_count: i32,
 }
 
+#[derive(Clone)]
 pub struct Header {
     pub version: i32,
     pub status: i32,
@@ -24,6 +29,15 @@ pub struct Header {
     pub num_buf: i32,
     pub buf_len: i32,
     pub buf_offset: i32,
+    pub var_bufs: Vec<VarBuf>,
+}
+
+// One of the (up to 4) sample buffers, files only use the first one while
+// the live telemetry rotates between them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VarBuf {
+    pub tick_count: i32,
+    pub buf_offset: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -37,6 +51,149 @@ pub struct VarHeader {
     pub unit: String,
 }
 
+impl VarHeader {
+    // High frequency variables (e.g. `LatAccel_ST`) store `count` sub-ticks
+    // per sample instead of one value per car.
+    pub fn is_time_series(&self) -> bool {
+        self.count_as_time != 0
+    }
+
+    // Time of each sub-tick relative to the sample's `SessionTime`, the last
+    // one lines up with the sample itself.
+    pub fn sub_tick_offsets(&self, tick_rate: i32) -> Vec<f64> {
+        let count = self.count.max(1);
+        let step = 1.0 / (tick_rate as f64 * count as f64);
+        (0..count)
+            .map(|index| -((count - 1 - index) as f64) * step)
+            .collect()
+    }
+}
+
+impl VarHeader {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![0; VAR_HEADER_BYTES_SIZE];
+        data[0..4].copy_from_slice(&self.r#type.to_le_bytes());
+        data[4..8].copy_from_slice(&self.offset.to_le_bytes());
+        data[8..12].copy_from_slice(&self.count.to_le_bytes());
+        data[12..13].copy_from_slice(&self.count_as_time.to_le_bytes());
+        write_str(&mut data[16..48], &self.name);
+        write_str(&mut data[48..112], &self.description);
+        write_str(&mut data[112..144], &self.unit);
+        data
+    }
+}
+
+impl DiskHeader {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(DISK_HEADER_BYTES_SIZE);
+        data.extend_from_slice(&self.start_date.to_le_bytes());
+        data.extend_from_slice(&self.start_time.to_le_bytes());
+        data.extend_from_slice(&self.end_time.to_le_bytes());
+        data.extend_from_slice(&self.lap_count.to_le_bytes());
+        data.extend_from_slice(&self.record_count.to_le_bytes());
+        data
+    }
+}
+
+impl Header {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![0; HEADER_BYTES_SIZE];
+        let fields = [
+            self.version,
+            self.status,
+            self.tick_rate,
+            self.sesion_info_update,
+            self.sesion_info_length,
+            self.sesion_info_offset,
+            self.num_vars,
+            self.var_header_offset,
+            self.num_buf,
+            self.buf_len,
+        ];
+        for (n, field) in fields.iter().enumerate() {
+            data[n * 4..(n + 1) * 4].copy_from_slice(&field.to_le_bytes());
+        }
+        for (n, buf) in self.var_bufs.iter().take(MAX_BUFS as usize).enumerate() {
+            let start = 48 + n * 16;
+            data[start..(start + 4)].copy_from_slice(&buf.tick_count.to_le_bytes());
+            data[(start + 4)..(start + 8)].copy_from_slice(&buf.buf_offset.to_le_bytes());
+        }
+        if self.var_bufs.is_empty() {
+            data[52..56].copy_from_slice(&self.buf_offset.to_le_bytes());
+        }
+        data
+    }
+}
+
+impl Header {
+    // Checks that everything the header points at is inside a file of
+    // `file_len` bytes, so a corrupt file is an error rather than a huge
+    // allocation or an out of bounds read.
+    pub(crate) fn validate(&self, file_len: u64) -> Result<(), Error> {
+        let within = |name: &str, offset: i32, len: i64| {
+            let end = offset as i64 + len;
+            if offset < 0 || len < 0 || end as u64 > file_len {
+                return Err(Error::InvalidHeader(format!(
+                    "{name} ({len} bytes at {offset}) is outside the file"
+                )));
+            }
+            Ok(())
+        };
+        within(
+            "session info",
+            self.sesion_info_offset,
+            self.sesion_info_length as i64,
+        )?;
+        within(
+            "var headers",
+            self.var_header_offset,
+            self.num_vars as i64 * VAR_HEADER_BYTES_SIZE as i64,
+        )?;
+        within("samples", self.buf_offset, 0)?;
+        if self.buf_len <= 0 {
+            return Err(Error::InvalidHeader(format!(
+                "sample length {} isn't positive",
+                self.buf_len
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl VarHeader {
+    // Bytes of a single value, None for types we don't know.
+    pub fn value_size(&self) -> Option<usize> {
+        match self.r#type {
+            0 | 1 => Some(1),
+            2..=4 => Some(4),
+            5 => Some(8),
+            _ => None,
+        }
+    }
+
+    // Checks that the var's values fit in a sample of `buf_len` bytes.
+    pub(crate) fn validate(&self, buf_len: i32) -> Result<(), Error> {
+        let size = self.value_size().ok_or_else(|| {
+            Error::InvalidHeader(format!("{} has unknown type {}", self.name, self.r#type))
+        })?;
+        let end = self.offset as i64 + size as i64 * self.count.max(1) as i64;
+        if self.offset < 0 || end > buf_len as i64 {
+            return Err(Error::InvalidHeader(format!(
+                "{} is outside the sample",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Zero padded, characters CP1252 can't represent become '?'.
+fn write_str(data: &mut [u8], value: &str) {
+    let encoded = CP1252.encode_lossy(value, b'?');
+    let len = encoded.len().min(data.len() - 1);
+    data[..len].copy_from_slice(&encoded[..len]);
+}
+
 impl From<Vec<u8>> for VarHeader {
     fn from(data: Vec<u8>) -> VarHeader {
         VarHeader {
@@ -55,7 +212,7 @@ impl From<Vec<u8>> for VarHeader {
 impl From<Vec<u8>> for DiskHeader {
     fn from(data: Vec<u8>) -> DiskHeader {
         DiskHeader {
-            start_date: f32::from_le_bytes(data[0..4].try_into().unwrap()),
+            start_date: i64::from_le_bytes(data[0..8].try_into().unwrap()),
             start_time: f64::from_le_bytes(data[8..16].try_into().unwrap()),
             end_time: f64::from_le_bytes(data[16..24].try_into().unwrap()),
             lap_count: i32::from_le_bytes(data[24..28].try_into().unwrap()),
@@ -66,6 +223,7 @@ impl From<Vec<u8>> for DiskHeader {
 
 impl From<Vec<u8>> for Header {
     fn from(data: Vec<u8>) -> Header {
+        let num_buf = i32::from_le_bytes(data[32..36].try_into().unwrap());
         Header {
             version: i32::from_le_bytes(data[0..4].try_into().unwrap()),
             status: i32::from_le_bytes(data[4..8].try_into().unwrap()),
@@ -75,9 +233,22 @@ impl From<Vec<u8>> for Header {
             sesion_info_offset: i32::from_le_bytes(data[20..24].try_into().unwrap()),
             num_vars: i32::from_le_bytes(data[24..28].try_into().unwrap()),
             var_header_offset: i32::from_le_bytes(data[28..32].try_into().unwrap()),
-            num_buf: i32::from_le_bytes(data[32..36].try_into().unwrap()),
+            num_buf,
             buf_len: i32::from_le_bytes(data[36..40].try_into().unwrap()),
             buf_offset: i32::from_le_bytes(data[52..56].try_into().unwrap()),
+            var_bufs: (0..num_buf.clamp(0, MAX_BUFS))
+                .map(|n| {
+                    let start = 48 + n as usize * 16;
+                    VarBuf {
+                        tick_count: i32::from_le_bytes(
+                            data[start..(start + 4)].try_into().unwrap(),
+                        ),
+                        buf_offset: i32::from_le_bytes(
+                            data[(start + 4)..(start + 8)].try_into().unwrap(),
+                        ),
+                    }
+                })
+                .collect(),
         }
     }
 }
@@ -1,13 +1,45 @@
+pub mod analysis;
+pub mod anonymize;
+#[cfg(feature = "tokio")]
+mod async_reader;
+#[cfg(feature = "rayon")]
+pub mod batch;
+pub mod catalogue;
 pub mod constants;
+mod error;
+pub mod event;
+pub mod export;
 mod headers;
+mod live_reader;
+pub mod replay;
+pub mod resample;
+pub mod results;
 mod samples;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session_diff;
 mod session_info;
-
-use std::io::{Read, Seek, SeekFrom};
-
+pub mod stream_reader;
+mod summary;
+pub mod trim;
+mod writer;
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    iter::Take,
+    path::Path,
+};
+
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncIbtReader;
+pub use error::Error;
 use headers::{DiskHeader, Header, VarHeader, DISK_HEADER_BYTES_SIZE, HEADER_BYTES_SIZE};
-use samples::Samples;
-use session_info::SessionInfo;
+pub use live_reader::LiveReader;
+use samples::{SampleValue, Samples};
+use session_info::{Session, SessionInfo};
+pub use stream_reader::IbtStreamReader;
+pub use summary::IbtSummary;
 use yore::code_pages::CP1252;
 
 use crate::headers::VAR_HEADER_BYTES_SIZE;
@@ -23,39 +55,66 @@ pub struct IbtReader {
     pub session_info: SessionInfo,
 }
 
+// A contiguous run of samples recorded during one session, joined with the
+// session's entry from the session info when there is one.
+pub struct SessionSamples {
+    pub session_num: i32,
+    pub session: Option<Session>,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl SessionSamples {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
 impl IbtReader {
-    pub fn new(mut buffer: Box<dyn ReadSeek>) -> IbtReader {
-        let header = Header::from(read_bytes_file(&mut buffer, 0, HEADER_BYTES_SIZE).unwrap());
-        let disk_header = DiskHeader::from(
-            read_bytes_file(&mut buffer, DISK_HEADER_BYTES_SIZE, HEADER_BYTES_SIZE).unwrap(),
-        );
+    pub fn new(buffer: Box<dyn ReadSeek>) -> IbtReader {
+        IbtReader::try_new(buffer).unwrap()
+    }
+
+    pub fn try_new(mut buffer: Box<dyn ReadSeek>) -> Result<IbtReader, Error> {
+        let file_len = buffer.seek(SeekFrom::End(0))?;
+        let header = Header::from(read_bytes_file(&mut buffer, 0, HEADER_BYTES_SIZE)?);
+        header.validate(file_len)?;
+        let disk_header = DiskHeader::from(read_bytes_file(
+            &mut buffer,
+            HEADER_BYTES_SIZE,
+            DISK_HEADER_BYTES_SIZE,
+        )?);
         let session_info_data = read_bytes_file(
             &mut buffer,
             header.sesion_info_offset as usize,
             header.sesion_info_length as usize,
-        )
-        .unwrap();
-        let session_info = serde_yaml::from_str(&CP1252.decode(&session_info_data)).unwrap();
-
-        let vars_data = get_var_header(&mut buffer, &header);
-        let vars: Vec<VarHeader> = (0..header.num_vars)
-            .map(|n| {
-                let start = n as usize * VAR_HEADER_BYTES_SIZE;
-                let end = start + VAR_HEADER_BYTES_SIZE;
-                VarHeader::from(vars_data[start..end].to_vec())
-            })
-            .collect();
+        )?;
+        let session_info = parse_session_info(&session_info_data)?;
+
+        let vars_data = get_var_header(&mut buffer, &header)?;
+        let vars = parse_vars(&vars_data, header.num_vars);
+        for var in &vars {
+            var.validate(header.buf_len)?;
+        }
 
-        IbtReader {
+        Ok(IbtReader {
             file: Box::new(buffer),
             header,
             vars,
             disk_header,
             session_info,
-        }
+        })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<IbtReader, Error> {
+        IbtReader::try_new(Box::new(File::open(path)?))
     }
 
-    pub fn samples(&mut self) -> Samples {
+    pub fn samples(&mut self) -> Samples<'_> {
         Samples {
             current: 0,
             buf_offset: self.header.buf_offset,
@@ -64,30 +123,99 @@ impl IbtReader {
         }
     }
 
+    pub fn session_samples(&mut self, session: &SessionSamples) -> Take<Samples<'_>> {
+        let mut samples = self.samples();
+        samples.current = session.start as i32;
+        samples.take(session.len())
+    }
+
+    pub fn sessions(&mut self) -> Vec<SessionSamples> {
+        let session_num = match self.find_var("SessionNum".to_string()) {
+            Some(var) => var,
+            None => return vec![],
+        };
+
+        let mut ranges: Vec<SessionSamples> = Vec::new();
+        for (index, sample) in self.samples().enumerate() {
+            let num = sample.get_by_header(&session_num).unwrap().int();
+            match ranges.last_mut() {
+                Some(range) if range.session_num == num => range.end = index + 1,
+                _ => ranges.push(SessionSamples {
+                    session_num: num,
+                    session: None,
+                    start: index,
+                    end: index + 1,
+                }),
+            }
+        }
+
+        let sessions = &self.session_info.session_info.sessions;
+        for range in ranges.iter_mut() {
+            range.session = sessions
+                .iter()
+                .find(|session| session.session_num == range.session_num)
+                .cloned();
+        }
+        ranges
+    }
+
+    // Flattens a high frequency variable into one value per sub-tick, e.g.
+    // 360 Hz for a variable with 6 sub-ticks recorded at 60 Hz.
+    pub fn time_series(&mut self, var: &VarHeader) -> Option<Vec<(f64, SampleValue)>> {
+        if !var.is_time_series() {
+            return None;
+        }
+        let session_time = self.find_var("SessionTime".to_string())?;
+        let offsets = var.sub_tick_offsets(self.header.tick_rate);
+        let series = self
+            .samples()
+            .flat_map(|sample| {
+                let time = sample.get_by_header(&session_time).unwrap().float_64();
+                offsets
+                    .iter()
+                    .map(move |offset| time + offset)
+                    .zip(sample.get_all_by_header(var))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Some(series)
+    }
+
     pub fn find_var(&self, name: String) -> Option<VarHeader> {
         self.vars.iter().find(|var| var.name == name).cloned()
     }
 }
 
-fn get_var_header(file: &mut dyn ReadSeek, header: &Header) -> Vec<u8> {
+fn parse_session_info(data: &[u8]) -> Result<SessionInfo, serde_yaml::Error> {
+    serde_yaml::from_str(&CP1252.decode(data))
+}
+
+fn parse_vars(data: &[u8], num_vars: i32) -> Vec<VarHeader> {
+    (0..num_vars)
+        .map(|n| {
+            let start = n as usize * VAR_HEADER_BYTES_SIZE;
+            let end = start + VAR_HEADER_BYTES_SIZE;
+            VarHeader::from(data[start..end].to_vec())
+        })
+        .collect()
+}
+
+fn get_var_header(file: &mut dyn ReadSeek, header: &Header) -> io::Result<Vec<u8>> {
     let buffer_size = header.num_vars as usize * VAR_HEADER_BYTES_SIZE;
-    read_bytes_file(file, header.var_header_offset as usize, buffer_size).unwrap()
+    read_bytes_file(file, header.var_header_offset as usize, buffer_size)
 }
 
-fn read_bytes_file(file: &mut dyn ReadSeek, from: usize, size: usize) -> Result<Vec<u8>, ()> {
+fn read_bytes_file(file: &mut dyn ReadSeek, from: usize, size: usize) -> io::Result<Vec<u8>> {
     let mut buffer: Vec<u8> = vec![0; size];
-    file.seek(SeekFrom::Start(from as u64)).unwrap();
-    match file.read_exact(&mut buffer).map_err(|_e| ()) {
-        Ok(_) => Ok(buffer),
-        Err(_) => Err(()),
-    }
+    file.seek(SeekFrom::Start(from as u64))?;
+    file.read_exact(&mut buffer)?;
+    Ok(buffer)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::constants::Flags;
-    use crate::samples::{Sample, SampleValue};
-    use std::fs::File;
+    use crate::samples::Sample;
 
     use super::*;
 
@@ -107,11 +235,11 @@ mod tests {
         assert_eq!(reader.header.buf_len, 1039);
         assert_eq!(reader.header.buf_offset, 52080);
 
-        assert_eq!(reader.disk_header.start_date, 1e-45);
-        assert_eq!(reader.disk_header.start_time, 0_f64);
-        assert_eq!(reader.disk_header.end_time, 1.105135407938237e-309);
-        assert_eq!(reader.disk_header.record_count, 0);
-        assert_eq!(reader.disk_header.lap_count, 0);
+        assert_eq!(reader.disk_header.start_date, 1681113045);
+        assert_eq!(reader.disk_header.start_time, 54.049999745686165);
+        assert_eq!(reader.disk_header.end_time, 110.34999974568296);
+        assert_eq!(reader.disk_header.record_count, 3371);
+        assert_eq!(reader.disk_header.lap_count, 1);
 
         let weekend_info = &reader.session_info.weekend_info;
         assert_eq!(weekend_info.track_name, "spielberg gp");
@@ -191,6 +319,78 @@ mod tests {
         assert_eq!(second_sample.bitfield() & Flags::Checkered as u32, 0);
     }
 
+    #[test]
+    fn test_sessions() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+
+        let sessions = reader.sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_num, 0);
+        assert_eq!(sessions[0].start, 0);
+        assert_eq!(sessions[0].end, 3371);
+        let session = sessions[0].session.as_ref().unwrap();
+        assert_eq!(session.session_type, "Offline Testing");
+
+        let samples: Vec<Sample> = reader.session_samples(&sessions[0]).collect();
+        assert_eq!(samples.len(), 3371);
+    }
+
+    #[test]
+    fn test_bool_values() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let on_pit_road = reader.find_var("OnPitRoad".to_string()).unwrap();
+        let in_pit_stall = reader.find_var("PlayerCarInPitStall".to_string()).unwrap();
+        let is_on_track = reader.find_var("IsOnTrack".to_string()).unwrap();
+
+        // The car starts in its pit stall and drives out onto the track.
+        let samples: Vec<Sample> = reader.samples().collect();
+        assert_eq!(
+            samples[0].get_by_header(&on_pit_road).unwrap(),
+            SampleValue::Bool(true)
+        );
+        assert_eq!(
+            samples[0].get_by_header(&in_pit_stall).unwrap(),
+            SampleValue::Bool(true)
+        );
+        assert_eq!(
+            samples[420].get_by_header(&in_pit_stall).unwrap(),
+            SampleValue::Bool(false)
+        );
+        assert_eq!(
+            samples[900].get_by_header(&on_pit_road).unwrap(),
+            SampleValue::Bool(false)
+        );
+        assert_eq!(
+            samples[900].get_by_header(&is_on_track).unwrap(),
+            SampleValue::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_time_series() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let torque = reader
+            .find_var("SteeringWheelTorque_ST".to_string())
+            .unwrap();
+        assert!(torque.is_time_series());
+        assert!(!reader.find_var("RPM".to_string()).unwrap().is_time_series());
+
+        let series = reader.time_series(&torque).unwrap();
+        assert_eq!(series.len(), 3371 * 6);
+        assert_eq!(series[5].0, 54.049999745686165);
+        assert!((series[5].0 - series[4].0 - 1.0 / 360.0).abs() < 1e-9);
+        assert!((series[6].0 - series[5].0 - 1.0 / 360.0).abs() < 1e-3);
+
+        let samples: Vec<Sample> = reader.samples().take(1).collect();
+        let sub_ticks = samples[0].get_all_by_header(&torque);
+        assert_eq!(sub_ticks.len(), 6);
+        assert_eq!(samples[0].get_by_header_at(&torque, 5), Some(series[5].1));
+        assert_eq!(samples[0].get_by_header_at(&torque, 6), None);
+    }
+
     #[test]
     fn test_parsing_ai_race() {
         let file = File::open("./test/fixtures/ai_race/practice.ibt").unwrap();
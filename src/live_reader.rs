@@ -0,0 +1,332 @@
+use std::{fs, io, iter, path::Path};
+
+use serde_yaml::Value;
+use yore::code_pages::CP1252;
+
+use crate::{
+    error::Error,
+    headers::{Header, VarBuf, VarHeader, HEADER_BYTES_SIZE, VAR_HEADER_BYTES_SIZE},
+    parse_vars,
+    samples::Sample,
+    session_diff::{diff, SessionInfoDiff},
+    session_info::SessionInfo,
+};
+
+// Status bit set by the sim while it's running.
+const STATUS_CONNECTED: i32 = 1;
+// A buffer can be overwritten while it's being copied, the copy is retried
+// this many times before giving up until the next poll.
+const READ_ATTEMPTS: usize = 3;
+
+// Reads iRacing's live telemetry from any byte region laid out like its
+// shared memory (`Local\IRSDKMemMapFileName`), e.g. a memory mapping or a
+// snapshot of one. The region is read again on every call, so a mapping that
+// the sim keeps writing to is followed as it changes.
+pub struct LiveReader<S: AsRef<[u8]>> {
+    source: S,
+    pub header: Header,
+    pub vars: Vec<VarHeader>,
+    pub session_info: SessionInfo,
+    // untyped copy of the session info, used to work out what changed
+    session_yaml: Value,
+    last_tick: Option<i32>,
+}
+
+impl LiveReader<Vec<u8>> {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<LiveReader<Vec<u8>>, Error> {
+        LiveReader::new(fs::read(path)?)
+    }
+}
+
+impl<S: AsRef<[u8]>> LiveReader<S> {
+    pub fn new(source: S) -> Result<LiveReader<S>, Error> {
+        let header = read_header(source.as_ref())?;
+        let vars = read_vars(source.as_ref(), &header)?;
+        let (session_info, session_yaml) = read_session_info(source.as_ref(), &header)?;
+        Ok(LiveReader {
+            source,
+            header,
+            vars,
+            session_info,
+            session_yaml,
+            last_tick: None,
+        })
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.header.status & STATUS_CONNECTED != 0
+    }
+
+    // Reads the header again, re-parsing the var headers when the layout
+    // changed and the session info when `sesion_info_update` moved on, in
+    // which case what changed in it is returned.
+    pub fn refresh(&mut self) -> Result<Option<SessionInfoDiff>, Error> {
+        let header = read_header(self.source.as_ref())?;
+        if self.restarted(&header) {
+            self.last_tick = None;
+        }
+        self.update_layout(&header)?;
+        let mut changes = None;
+        if header.sesion_info_update != self.header.sesion_info_update {
+            let (session_info, session_yaml) = read_session_info(self.source.as_ref(), &header)?;
+            changes = Some(SessionInfoDiff {
+                update: header.sesion_info_update,
+                changes: diff(&self.session_yaml, &session_yaml),
+            });
+            self.session_info = session_info;
+            self.session_yaml = session_yaml;
+        }
+        self.header = header;
+        Ok(changes)
+    }
+
+    // The sample in the freshest buffer, `None` when it was already returned.
+    // The session info is left for `refresh` to pick up, so its changes
+    // aren't missed when samples are read first.
+    pub fn latest(&mut self) -> Option<Sample> {
+        for _ in 0..READ_ATTEMPTS {
+            let header = read_header(self.source.as_ref()).ok()?;
+            if self.restarted(&header) {
+                self.last_tick = None;
+            }
+            self.update_layout(&header).ok()?;
+            let buf = freshest(&header.var_bufs)?;
+            if self.last_tick.is_some_and(|tick| buf.tick_count <= tick) {
+                return None;
+            }
+
+            let from = buf.buf_offset as usize;
+            let data = slice(self.source.as_ref(), from, header.buf_len as usize)
+                .ok()?
+                .to_vec();
+
+            let after = read_header(self.source.as_ref()).ok()?;
+            let unchanged = after.var_bufs.iter().any(|other| {
+                other.buf_offset == buf.buf_offset && other.tick_count == buf.tick_count
+            });
+            if unchanged {
+                self.last_tick = Some(buf.tick_count);
+                self.header.status = header.status;
+                self.header.num_buf = header.num_buf;
+                self.header.var_bufs = header.var_bufs;
+                return Some(Sample::from(data));
+            }
+        }
+        None
+    }
+
+    // Every new sample that's available right now, polling again later
+    // continues where this left off.
+    pub fn samples(&mut self) -> impl Iterator<Item = Sample> + '_ {
+        iter::from_fn(move || self.latest())
+    }
+
+    pub fn find_var(&self, name: String) -> Option<VarHeader> {
+        self.vars.iter().find(|var| var.name == name).cloned()
+    }
+
+    // Reads the var headers again when they moved or the sample layout
+    // changed, samples can't be decoded with the old ones.
+    fn update_layout(&mut self, header: &Header) -> Result<(), Error> {
+        if header.num_vars != self.header.num_vars
+            || header.var_header_offset != self.header.var_header_offset
+            || header.buf_len != self.header.buf_len
+        {
+            self.vars = read_vars(self.source.as_ref(), header)?;
+            self.header.num_vars = header.num_vars;
+            self.header.var_header_offset = header.var_header_offset;
+            self.header.buf_len = header.buf_len;
+        }
+        Ok(())
+    }
+
+    // The sim was restarted or loaded another session, its tick count starts
+    // again from 0.
+    fn restarted(&self, header: &Header) -> bool {
+        let went_back = match (self.last_tick, freshest(&header.var_bufs)) {
+            (Some(last_tick), Some(buf)) => buf.tick_count < last_tick,
+            _ => false,
+        };
+        went_back
+            || header.status != self.header.status
+            || header.num_vars != self.header.num_vars
+            || header.var_header_offset != self.header.var_header_offset
+            || header.buf_len != self.header.buf_len
+    }
+}
+
+fn freshest(var_bufs: &[VarBuf]) -> Option<VarBuf> {
+    var_bufs.iter().max_by_key(|buf| buf.tick_count).copied()
+}
+
+fn read_header(data: &[u8]) -> Result<Header, Error> {
+    Ok(Header::from(slice(data, 0, HEADER_BYTES_SIZE)?.to_vec()))
+}
+
+fn read_vars(data: &[u8], header: &Header) -> Result<Vec<VarHeader>, Error> {
+    let size = header.num_vars as usize * VAR_HEADER_BYTES_SIZE;
+    let vars_data = slice(data, header.var_header_offset as usize, size)?;
+    let vars = parse_vars(vars_data, header.num_vars);
+    for var in &vars {
+        var.validate(header.buf_len)?;
+    }
+    Ok(vars)
+}
+
+fn read_session_info(data: &[u8], header: &Header) -> Result<(SessionInfo, Value), Error> {
+    let session_info_data = slice(
+        data,
+        header.sesion_info_offset as usize,
+        header.sesion_info_length as usize,
+    )?;
+    // The live session info is padded with zeroes up to its maximum length.
+    let end = session_info_data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(session_info_data.len());
+    let yaml = CP1252.decode(&session_info_data[..end]);
+    Ok((serde_yaml::from_str(&yaml)?, serde_yaml::from_str(&yaml)?))
+}
+
+fn slice(data: &[u8], from: usize, size: usize) -> io::Result<&[u8]> {
+    data.get(from..from + size)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_diff::Change;
+
+    fn write_i32(data: &mut [u8], offset: usize, value: i32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn test_live_reader() {
+        let mut reader = LiveReader::from_file("./test/fixtures/amg.ibt").unwrap();
+        assert!(reader.is_connected());
+        assert_eq!(
+            reader.header.var_bufs,
+            vec![VarBuf {
+                tick_count: 713,
+                buf_offset: 52080
+            }]
+        );
+        assert_eq!(reader.session_info.weekend_info.track_name, "spielberg gp");
+
+        let session_time = reader.find_var("SessionTime".to_string()).unwrap();
+        let first = reader.latest().unwrap();
+        assert_eq!(
+            first.get_by_header(&session_time).unwrap().float_64(),
+            54.049999745686165
+        );
+        assert!(reader.latest().is_none());
+
+        // A second, fresher buffer pointing at the next record.
+        let data = reader.source_mut();
+        write_i32(data, 32, 2);
+        write_i32(data, 64, 714);
+        write_i32(data, 68, 52080 + 1039);
+        assert!(reader.refresh().unwrap().is_none());
+        let samples: Vec<Sample> = reader.samples().collect();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(
+            samples[0].get_by_header(&session_time).unwrap().float_64(),
+            54.06666641235283
+        );
+
+        // The sim rewrites the session info and bumps the update counter.
+        let data = reader.source_mut();
+        let yaml = String::from_utf8_lossy(&data[38592..(38592 + 13488)]).to_string();
+        let from = 38592 + yaml.find("spielberg gp").unwrap();
+        data[from..(from + 12)].copy_from_slice(b"spielberg xx");
+        write_i32(data, 12, 1);
+        let diff = reader.refresh().unwrap().unwrap();
+        assert_eq!(diff.update, 1);
+        assert_eq!(
+            diff.changes,
+            vec![Change::Changed {
+                path: "WeekendInfo.TrackName".to_string(),
+                old: Value::from("spielberg gp"),
+                new: Value::from("spielberg xx"),
+            }]
+        );
+        assert_eq!(reader.session_info.weekend_info.track_name, "spielberg xx");
+        assert!(reader.refresh().unwrap().is_none());
+
+        // The sim restarts and counts ticks from 0 again.
+        let data = reader.source_mut();
+        write_i32(data, 48, 0);
+        write_i32(data, 64, 5);
+        assert!(reader.latest().is_some());
+        assert!(reader.latest().is_none());
+        write_i32(reader.source_mut(), 64, 6);
+        assert!(reader.latest().is_some());
+
+        // Also noticed when it's the refresh that sees it first.
+        write_i32(reader.source_mut(), 64, 2);
+        assert!(reader.refresh().unwrap().is_none());
+        assert!(reader.latest().is_some());
+
+        // Reconnecting starts over even if the tick count is still ahead.
+        write_i32(reader.source_mut(), 4, 0);
+        assert!(reader.latest().is_some());
+        write_i32(reader.source_mut(), 4, 1);
+        assert!(reader.latest().is_some());
+        assert!(reader.latest().is_none());
+    }
+
+    #[test]
+    fn test_live_reader_samples_first() {
+        let mut reader = LiveReader::from_file("./test/fixtures/amg.ibt").unwrap();
+        assert!(reader.latest().is_some());
+
+        // The session info is rewritten while a new sample is published,
+        // reading the sample first doesn't hide the update from `refresh`.
+        let data = reader.source_mut();
+        let yaml = String::from_utf8_lossy(&data[38592..(38592 + 13488)]).to_string();
+        let from = 38592 + yaml.find("spielberg gp").unwrap();
+        data[from..(from + 12)].copy_from_slice(b"spielberg xx");
+        write_i32(data, 12, 1);
+        write_i32(data, 48, 714);
+        assert_eq!(reader.samples().count(), 1);
+        let diff = reader.refresh().unwrap().unwrap();
+        assert_eq!(diff.update, 1);
+        assert_eq!(
+            diff.under("WeekendInfo")
+                .map(Change::path)
+                .collect::<Vec<_>>(),
+            vec!["WeekendInfo.TrackName"]
+        );
+        assert_eq!(reader.session_info.weekend_info.track_name, "spielberg xx");
+
+        // Fewer vars, which the next sample is decoded with.
+        let data = reader.source_mut();
+        write_i32(data, 24, 266);
+        write_i32(data, 48, 715);
+        assert!(reader.latest().is_some());
+        assert_eq!(reader.vars.len(), 266);
+        assert!(reader.refresh().unwrap().is_none());
+
+        // A var pointing outside the sample is an error, not a panic later.
+        let data = reader.source_mut();
+        write_i32(data, 148, 5000);
+        write_i32(data, 24, 267);
+        write_i32(data, 48, 716);
+        assert!(reader.latest().is_none());
+        assert!(matches!(reader.refresh(), Err(Error::InvalidHeader(_))));
+        assert!(matches!(
+            LiveReader::new(reader.source().clone()),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+}
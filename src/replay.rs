@@ -0,0 +1,281 @@
+use std::{
+    io,
+    net::{ToSocketAddrs, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::Error,
+    headers::{Header, VarBuf, HEADER_BYTES_SIZE, VAR_HEADER_BYTES_SIZE},
+    read_bytes_file, IbtReader,
+};
+
+// Same as the sim, which rotates between 3 sample buffers.
+const NUM_BUFS: usize = 3;
+const VAR_HEADER_OFFSET: usize = 144;
+const STATUS_CONNECTED: i32 = 1;
+
+// Plays an .ibt file back into a byte buffer laid out like iRacing's shared
+// memory, so anything reading live telemetry (e.g. `LiveReader`) can be run
+// against a recording. Time only moves on through `tick`, which makes it
+// deterministic, `run` drives it from the wall clock.
+pub struct Replay {
+    reader: IbtReader,
+    header: Header,
+    memory: Vec<u8>,
+    records: usize,
+    position: usize,
+    speed: f64,
+    paused: bool,
+    looping: bool,
+    finished: bool,
+    // ticks owed but not published yet, less than one
+    pending: f64,
+}
+
+impl Replay {
+    pub fn new(mut reader: IbtReader) -> Result<Replay, Error> {
+        let session_info = read_bytes_file(
+            &mut reader.file,
+            reader.header.sesion_info_offset as usize,
+            reader.header.sesion_info_length as usize,
+        )?;
+        let buf_len = reader.header.buf_len as usize;
+        let session_info_offset = VAR_HEADER_OFFSET + reader.vars.len() * VAR_HEADER_BYTES_SIZE;
+        let buf_offset = session_info_offset + session_info.len();
+
+        let header = Header {
+            version: reader.header.version,
+            status: STATUS_CONNECTED,
+            tick_rate: reader.header.tick_rate,
+            sesion_info_update: 0,
+            sesion_info_length: session_info.len() as i32,
+            sesion_info_offset: session_info_offset as i32,
+            num_vars: reader.vars.len() as i32,
+            var_header_offset: VAR_HEADER_OFFSET as i32,
+            num_buf: NUM_BUFS as i32,
+            buf_len: buf_len as i32,
+            buf_offset: buf_offset as i32,
+            var_bufs: (0..NUM_BUFS)
+                .map(|n| VarBuf {
+                    tick_count: -1,
+                    buf_offset: (buf_offset + n * buf_len) as i32,
+                })
+                .collect(),
+        };
+
+        let mut memory = vec![0; buf_offset + NUM_BUFS * buf_len];
+        for (n, var) in reader.vars.iter().enumerate() {
+            let start = VAR_HEADER_OFFSET + n * VAR_HEADER_BYTES_SIZE;
+            memory[start..(start + VAR_HEADER_BYTES_SIZE)].copy_from_slice(&var.to_bytes());
+        }
+        memory[session_info_offset..buf_offset].copy_from_slice(&session_info);
+        memory[0..HEADER_BYTES_SIZE].copy_from_slice(&header.to_bytes());
+
+        Ok(Replay {
+            records: reader.disk_header.record_count.max(0) as usize,
+            reader,
+            header,
+            memory,
+            position: 0,
+            speed: 1.0,
+            paused: false,
+            looping: false,
+            finished: false,
+            pending: 0.0,
+        })
+    }
+
+    // The shared memory layout, as the sim would publish it.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // The most recently published sample.
+    pub fn latest(&self) -> Option<&[u8]> {
+        let buf = self
+            .header
+            .var_bufs
+            .iter()
+            .filter(|buf| buf.tick_count >= 0)
+            .max_by_key(|buf| buf.tick_count)?;
+        let start = buf.buf_offset as usize;
+        Some(&self.memory[start..(start + self.header.buf_len as usize)])
+    }
+
+    // Index of the next record to be published.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    // Seconds from the start of the recording.
+    pub fn seek(&mut self, seconds: f64) {
+        let record = (seconds.max(0.0) * self.header.tick_rate as f64) as usize;
+        self.position = record.min(self.records);
+        self.pending = 0.0;
+        if self.finished && self.position < self.records {
+            self.finished = false;
+            self.set_status(STATUS_CONNECTED);
+        }
+    }
+
+    // Moves the replay `elapsed` seconds forward (scaled by the speed) and
+    // publishes every record that became due. Returns how many were.
+    pub fn tick(&mut self, elapsed: Duration) -> Result<usize, Error> {
+        if self.paused || self.finished {
+            return Ok(0);
+        }
+        self.pending += elapsed.as_secs_f64() * self.speed * self.header.tick_rate as f64;
+        let due = self.pending.floor();
+        self.pending -= due;
+
+        let mut published = 0;
+        for _ in 0..due as usize {
+            if !self.publish()? {
+                break;
+            }
+            published += 1;
+        }
+        Ok(published)
+    }
+
+    // Plays the replay in real time, `on_tick` is called after every tick
+    // and stops the replay by returning false.
+    pub fn run<F: FnMut(&mut Replay) -> bool>(&mut self, mut on_tick: F) -> Result<(), Error> {
+        let interval = Duration::from_secs_f64(1.0 / self.header.tick_rate.max(1) as f64);
+        let mut last = Instant::now();
+        while !self.finished {
+            thread::sleep(interval);
+            let now = Instant::now();
+            self.tick(now - last)?;
+            last = now;
+            if !on_tick(self) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Sends the most recent sample as a single datagram.
+    pub fn send_latest<A: ToSocketAddrs>(&self, socket: &UdpSocket, addr: A) -> io::Result<usize> {
+        match self.latest() {
+            Some(data) => socket.send_to(data, addr),
+            None => Ok(0),
+        }
+    }
+
+    fn publish(&mut self) -> Result<bool, Error> {
+        if self.position >= self.records {
+            if !self.looping || self.records == 0 {
+                self.finished = true;
+                self.set_status(0);
+                return Ok(false);
+            }
+            self.position = 0;
+        }
+
+        let length = self.header.buf_len as usize;
+        let from = self.reader.header.buf_offset as usize + self.position * length;
+        let data = read_bytes_file(&mut self.reader.file, from, length)?;
+
+        let tick_count = self
+            .header
+            .var_bufs
+            .iter()
+            .map(|buf| buf.tick_count)
+            .max()
+            .unwrap_or(-1)
+            + 1;
+        let buf = &mut self.header.var_bufs[tick_count as usize % NUM_BUFS];
+        let start = buf.buf_offset as usize;
+        self.memory[start..(start + length)].copy_from_slice(&data);
+        buf.tick_count = tick_count;
+        self.memory[0..HEADER_BYTES_SIZE].copy_from_slice(&self.header.to_bytes());
+
+        self.position += 1;
+        Ok(true)
+    }
+
+    fn set_status(&mut self, status: i32) {
+        self.header.status = status;
+        self.memory[0..HEADER_BYTES_SIZE].copy_from_slice(&self.header.to_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LiveReader;
+
+    #[test]
+    fn test_replay() {
+        let mut replay = Replay::new(IbtReader::open("./test/fixtures/amg.ibt").unwrap()).unwrap();
+        let mut recording = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let session_time = recording.find_var("SessionTime".to_string()).unwrap();
+        let times: Vec<f64> = recording
+            .samples()
+            .map(|sample| sample.get_by_header(&session_time).unwrap().float_64())
+            .collect();
+
+        let live_time = |replay: &Replay| {
+            let mut live = LiveReader::new(replay.memory()).unwrap();
+            assert_eq!(live.session_info.weekend_info.track_name, "spielberg gp");
+            let var = live.find_var("SessionTime".to_string()).unwrap();
+            (
+                live.is_connected(),
+                live.latest()
+                    .unwrap()
+                    .get_by_header(&var)
+                    .unwrap()
+                    .float_64(),
+            )
+        };
+
+        assert_eq!(replay.tick(Duration::from_millis(500)).unwrap(), 30);
+        assert_eq!(live_time(&replay), (true, times[29]));
+
+        replay.set_speed(2.0);
+        assert_eq!(replay.tick(Duration::from_millis(500)).unwrap(), 60);
+        assert_eq!(replay.position(), 90);
+
+        replay.pause();
+        assert_eq!(replay.tick(Duration::from_secs(1)).unwrap(), 0);
+        replay.resume();
+
+        replay.seek(3370.0 / 60.0);
+        assert_eq!(replay.tick(Duration::from_secs(1)).unwrap(), 1);
+        assert!(replay.is_finished());
+        assert_eq!(live_time(&replay), (false, times[3370]));
+
+        replay.set_looping(true);
+        replay.set_speed(1.0);
+        replay.seek(3370.0 / 60.0);
+        assert_eq!(replay.tick(Duration::from_millis(50)).unwrap(), 3);
+        assert_eq!(live_time(&replay), (true, times[1]));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::IbtReader;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelKind {
+    // floats, interpolated and averaged when resampling
+    Continuous,
+    // ints, bitfields, enums and bools, which only ever take the last value
+    Discrete,
+}
+
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub name: String,
+    pub unit: String,
+    pub kind: ChannelKind,
+    pub time: Vec<f64>,
+    pub values: Vec<f64>,
+}
+
+pub struct Envelope {
+    pub time: Vec<f64>,
+    pub min: Vec<f64>,
+    pub max: Vec<f64>,
+}
+
+// Reads the given channels against `SessionTime` in a single pass over the samples.
+pub fn read_channels(reader: &mut IbtReader, names: &[&str]) -> Option<Vec<Channel>> {
+    let session_time = reader.find_var("SessionTime".to_string())?;
+    let vars = names
+        .iter()
+        .map(|name| reader.find_var(name.to_string()))
+        .collect::<Option<Vec<_>>>()?;
+
+    // high frequency variables are flattened so every channel gets its own time
+    let offsets: Vec<Vec<f64>> = vars
+        .iter()
+        .map(|var| match var.is_time_series() {
+            true => var.sub_tick_offsets(reader.header.tick_rate),
+            false => vec![0.0],
+        })
+        .collect();
+    let mut columns: Vec<(Vec<f64>, Vec<f64>)> = vec![(vec![], vec![]); vars.len()];
+    for sample in reader.samples() {
+        let time = sample.get_by_header(&session_time).unwrap().float_64();
+        for ((var, offsets), (times, values)) in vars.iter().zip(&offsets).zip(columns.iter_mut()) {
+            for (index, offset) in offsets.iter().enumerate() {
+                times.push(time + offset);
+                values.push(sample.get_by_header_at(var, index).unwrap().as_f64());
+            }
+        }
+    }
+
+    Some(
+        vars.into_iter()
+            .zip(columns)
+            .map(|(var, (time, values))| Channel {
+                kind: match var.r#type {
+                    4 | 5 => ChannelKind::Continuous,
+                    _ => ChannelKind::Discrete,
+                },
+                name: var.name,
+                unit: var.unit,
+                time,
+                values,
+            })
+            .collect(),
+    )
+}
+
+impl Channel {
+    pub fn from_reader(reader: &mut IbtReader, name: &str) -> Option<Channel> {
+        read_channels(reader, &[name])?.pop()
+    }
+
+    // Reduces the channel to `hz` samples per second, continuous channels are
+    // averaged over each bucket while discrete ones keep the last value.
+    pub fn decimate(&self, hz: f64) -> Channel {
+        let mut time = vec![];
+        let mut values = vec![];
+        for (start, bucket) in self.buckets(hz) {
+            time.push(start);
+            values.push(match self.kind {
+                ChannelKind::Continuous => bucket.iter().sum::<f64>() / bucket.len() as f64,
+                ChannelKind::Discrete => *bucket.last().unwrap(),
+            });
+        }
+        self.with(time, values)
+    }
+
+    // Min and max of every bucket, so peaks survive in a decimated chart.
+    pub fn envelope(&self, hz: f64) -> Envelope {
+        let mut envelope = Envelope {
+            time: vec![],
+            min: vec![],
+            max: vec![],
+        };
+        for (start, bucket) in self.buckets(hz) {
+            envelope.time.push(start);
+            envelope
+                .min
+                .push(bucket.iter().copied().fold(f64::INFINITY, f64::min));
+            envelope
+                .max
+                .push(bucket.iter().copied().fold(f64::NEG_INFINITY, f64::max));
+        }
+        envelope
+    }
+
+    pub fn resample_time(&self, grid: &[f64]) -> Channel {
+        let values = grid
+            .iter()
+            .map(|x| interpolate(&self.time, &self.values, *x, self.kind))
+            .collect();
+        self.with(grid.to_vec(), values)
+    }
+
+    // Resamples against another channel recorded at the same ticks, e.g.
+    // `LapDist` for a single lap. `axis` has to be increasing.
+    pub fn resample_by(&self, axis: &Channel, grid: &[f64]) -> Channel {
+        let time = grid
+            .iter()
+            .map(|x| interpolate(&axis.values, &self.time, *x, ChannelKind::Continuous))
+            .collect();
+        let values = grid
+            .iter()
+            .map(|x| interpolate(&axis.values, &self.values, *x, self.kind))
+            .collect();
+        self.with(time, values)
+    }
+
+    pub fn slice(&self, from: f64, to: f64) -> Channel {
+        let start = self.time.partition_point(|time| *time < from);
+        let end = self.time.partition_point(|time| *time <= to);
+        self.with(
+            self.time[start..end].to_vec(),
+            self.values[start..end].to_vec(),
+        )
+    }
+
+    // No buckets at all for a rate that isn't positive and finite.
+    fn buckets(&self, hz: f64) -> Vec<(f64, &[f64])> {
+        let first = match self.time.first() {
+            Some(first) if hz.is_finite() && hz > 0.0 => *first,
+            _ => return vec![],
+        };
+        let width = 1.0 / hz;
+        let mut buckets = vec![];
+        let mut start = 0;
+        while start < self.time.len() {
+            let index = ((self.time[start] - first) / width).floor();
+            let bucket_end = first + (index + 1.0) * width;
+            // every bucket takes at least one value, even if the times are off
+            let end = start + self.time[start..].partition_point(|time| *time < bucket_end);
+            let end = end.max(start + 1);
+            buckets.push((first + index * width, &self.values[start..end]));
+            start = end;
+        }
+        buckets
+    }
+
+    fn with(&self, time: Vec<f64>, values: Vec<f64>) -> Channel {
+        Channel {
+            name: self.name.clone(),
+            unit: self.unit.clone(),
+            kind: self.kind,
+            time,
+            values,
+        }
+    }
+}
+
+fn interpolate(xs: &[f64], ys: &[f64], x: f64, kind: ChannelKind) -> f64 {
+    let index = xs.partition_point(|value| *value <= x);
+    if index == 0 {
+        return ys.first().copied().unwrap_or(f64::NAN);
+    }
+    if index == xs.len() || kind == ChannelKind::Discrete {
+        return ys[index - 1];
+    }
+    let (x0, x1) = (xs[index - 1], xs[index]);
+    let (y0, y1) = (ys[index - 1], ys[index]);
+    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_resampling() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtReader::new(Box::new(file));
+        let channels = read_channels(&mut reader, &["Speed", "Gear", "LapDist"]).unwrap();
+        let (speed, gear, lap_dist) = (&channels[0], &channels[1], &channels[2]);
+        assert_eq!(speed.kind, ChannelKind::Continuous);
+        assert_eq!(gear.kind, ChannelKind::Discrete);
+        assert_eq!(speed.values.len(), 3371);
+
+        let decimated = speed.decimate(10.0);
+        assert_eq!(decimated.values.len(), 563);
+        assert!((decimated.time[1] - decimated.time[0] - 0.1).abs() < 1e-9);
+        let envelope = speed.envelope(10.0);
+        assert!(envelope
+            .min
+            .iter()
+            .zip(&decimated.values)
+            .zip(&envelope.max)
+            .all(|((min, avg), max)| min <= avg && avg <= max));
+        assert!(gear
+            .decimate(1.0)
+            .values
+            .iter()
+            .all(|gear| gear.fract() == 0.0));
+
+        for hz in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(speed.decimate(hz).values.is_empty());
+            assert!(speed.envelope(hz).time.is_empty());
+        }
+        // times that can't be bucketed still make progress
+        let broken = speed.with(vec![f64::NAN; 3], vec![1.0, 2.0, 3.0]);
+        assert_eq!(broken.decimate(10.0).values.len(), 3);
+
+        let grid = [60.0, 60.5, 61.0];
+        let resampled = speed.resample_time(&grid);
+        assert_eq!(resampled.values.len(), 3);
+        assert!(resampled.values[1] > 6.0 && resampled.values[1] < 7.0);
+
+        // the car rolls backwards for a bit at the end of the fixture
+        let forward = lap_dist.slice(60.0, 95.0);
+        let by_distance = speed.slice(60.0, 95.0).resample_by(&forward, &[500.0]);
+        assert!(by_distance.time[0] > 60.0 && by_distance.time[0] < 95.0);
+    }
+}
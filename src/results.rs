@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+use crate::session_info::{Driver, DriverInfo, ResultPosition, Session, SessionInfo};
+
+// A classified car, joined with its driver by `car_idx`.
+pub struct Classified<'a> {
+    pub result: &'a ResultPosition,
+    pub driver: Option<&'a Driver>,
+}
+
+// The session's results in finishing order, empty before they're published.
+pub fn classification<'a>(
+    session: &'a Session,
+    driver_info: &'a DriverInfo,
+) -> Vec<Classified<'a>> {
+    let mut classified: Vec<Classified> = session
+        .results_positions
+        .iter()
+        .flatten()
+        .map(|result| Classified {
+            result,
+            driver: driver_info
+                .drivers
+                .iter()
+                .find(|driver| driver.car_idx == result.car_idx),
+        })
+        .collect();
+    classified.sort_by_key(|classified| classified.result.position);
+    classified
+}
+
+pub fn classification_table(session: &Session, driver_info: &DriverInfo) -> String {
+    let classified = classification(session, driver_info);
+    let mut table = format!("{} ({})\n", session.session_name, session.session_type);
+    if classified.is_empty() {
+        table.push_str("no results\n");
+        return table;
+    }
+
+    let _ = writeln!(
+        table,
+        "{:>3} {:>3} {:>4}  {:<24} {:>4}  {:>10}  {:>9}  {:>3}  Status",
+        "Pos", "Cls", "#", "Driver", "Laps", "Gap", "Fastest", "Inc"
+    );
+    let leader = classified[0].result;
+    for Classified { result, driver } in &classified {
+        let gap = if result.position == leader.position {
+            format_time(result.time)
+        } else if result.lap < leader.lap {
+            let laps = leader.lap - result.lap;
+            format!("+{laps} lap{}", if laps == 1 { "" } else { "s" })
+        } else {
+            format!("+{:.3}", result.time - leader.time)
+        };
+        let _ = writeln!(
+            table,
+            "{:>3} {:>3} {:>4}  {:<24} {:>4}  {:>10}  {:>9}  {:>3}  {}",
+            result.position,
+            result.class_position + 1,
+            driver.map_or("", |driver| driver.car_number.as_str()),
+            driver.map_or("?", |driver| driver.user_name.as_str()),
+            result.laps_complete,
+            gap,
+            format_time(result.fastest_time),
+            result.incidents,
+            result.reason_out_str,
+        );
+    }
+    table
+}
+
+// A table for every session in the file.
+pub fn classification_tables(session_info: &SessionInfo) -> String {
+    session_info
+        .session_info
+        .sessions
+        .iter()
+        .map(|session| classification_table(session, &session_info.driver_info))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn print_classifications(session_info: &SessionInfo) {
+    print!("{}", classification_tables(session_info));
+}
+
+// m:ss.sss, iRacing uses negative times for laps that weren't set.
+fn format_time(seconds: f32) -> String {
+    if seconds <= 0.0 {
+        return "-".to_string();
+    }
+    let minutes = (seconds / 60.0).floor();
+    format!("{}:{:06.3}", minutes, seconds - minutes * 60.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IbtReader;
+
+    #[test]
+    fn test_classification() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let session_info = &mut reader.session_info;
+        assert!(session_info.session_info.sessions[0]
+            .results_positions
+            .is_none());
+        assert_eq!(
+            classification_tables(session_info),
+            "TESTING (Offline Testing)\nno results\n"
+        );
+
+        let results: Vec<ResultPosition> = serde_yaml::from_str(
+            "
+- Position: 2
+  ClassPosition: 1
+  CarIdx: 3
+  Lap: 1
+  Time: 180.5
+  FastestLap: 1
+  FastestTime: 94.25
+  LastTime: 94.25
+  LapsLed: 0
+  LapsComplete: 1
+  LapsDriven: 1.000
+  Incidents: 4
+  ReasonOutId: 0
+  ReasonOutStr: Running
+- Position: 1
+  ClassPosition: 0
+  CarIdx: 0
+  Lap: 2
+  Time: 185.125
+  FastestLap: 2
+  FastestTime: 92.5
+  LastTime: 92.5
+  LapsLed: 2
+  LapsComplete: 2
+  JokerLapsComplete: 0
+  LapsDriven: 2.000
+  Incidents: 0
+  ReasonOutId: 0
+  ReasonOutStr: Running
+",
+        )
+        .unwrap();
+        session_info.session_info.sessions[0].results_positions = Some(results);
+
+        let session = &session_info.session_info.sessions[0];
+        let classified = classification(session, &session_info.driver_info);
+        assert_eq!(classified.len(), 2);
+        assert_eq!(classified[0].result.car_idx, 0);
+        assert_eq!(
+            classified[0].driver.map(|driver| driver.user_name.as_str()),
+            Some("Georgi Martsenkov")
+        );
+        assert!(classified[1].driver.is_none());
+
+        let table = classification_table(session, &session_info.driver_info);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].contains("Georgi Martsenkov"));
+        assert!(lines[2].contains("3:05.125"));
+        assert!(lines[2].contains("1:32.500"));
+        assert!(lines[3].contains("+1 lap "));
+    }
+}
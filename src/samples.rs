@@ -1,6 +1,6 @@
 use crate::{headers::VarHeader, read_bytes_file, ReadSeek};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SampleValue {
     Char(char),
     Bool(bool),
@@ -58,6 +58,17 @@ impl SampleValue {
             panic!()
         }
     }
+
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            SampleValue::Char(x) => *x as u32 as f64,
+            SampleValue::Bool(x) => *x as u8 as f64,
+            SampleValue::Int(x) => *x as f64,
+            SampleValue::BitField(x) => *x as f64,
+            SampleValue::Float32(x) => *x as f64,
+            SampleValue::Float64(x) => *x,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -80,45 +91,61 @@ impl Iterator for Samples<'_> {
         match read_bytes_file(&mut self.file, from as usize, self.length as usize) {
             Ok(data) => {
                 self.current += 1;
-                Some(Sample { data })
+                Some(Sample::from(data))
             }
             Err(_) => None,
         }
     }
 }
 
+impl From<Vec<u8>> for Sample {
+    fn from(data: Vec<u8>) -> Sample {
+        Sample { data }
+    }
+}
+
 impl Sample {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
     pub fn get_by_header(&self, var: &VarHeader) -> Option<SampleValue> {
+        self.get_by_header_at(var, 0)
+    }
+
+    // Element `index` of an array variable, `None` when out of bounds.
+    pub fn get_by_header_at(&self, var: &VarHeader, index: usize) -> Option<SampleValue> {
+        if index >= var.count.max(1) as usize {
+            return None;
+        }
         let offset = var.offset as usize;
         match var.r#type {
-            0 => {
-                let size = 1;
-                Some(SampleValue::Char(self.data[offset + size] as char))
-            }
-            1 => {
-                let size = 1;
-                Some(SampleValue::Bool(self.data[offset + size] != 0))
-            }
+            0 => Some(SampleValue::Char(self.data[offset + index] as char)),
+            1 => Some(SampleValue::Bool(self.data[offset + index] != 0)),
             2 => {
                 let size = 4;
+                let offset = offset + index * size;
                 let value =
                     i32::from_le_bytes(self.data[offset..(offset + size)].try_into().unwrap());
                 Some(SampleValue::Int(value))
             }
             3 => {
                 let size = 4;
+                let offset = offset + index * size;
                 let value =
                     u32::from_le_bytes(self.data[offset..(offset + size)].try_into().unwrap());
                 Some(SampleValue::BitField(value))
             }
             4 => {
                 let size = 4;
+                let offset = offset + index * size;
                 let value =
                     f32::from_le_bytes(self.data[offset..(offset + size)].try_into().unwrap());
                 Some(SampleValue::Float32(value))
             }
             5 => {
                 let size = 8;
+                let offset = offset + index * size;
                 let value =
                     f64::from_le_bytes(self.data[offset..(offset + size)].try_into().unwrap());
                 Some(SampleValue::Float64(value))
@@ -126,4 +153,10 @@ impl Sample {
             _ => unimplemented!(),
         }
     }
+
+    pub fn get_all_by_header(&self, var: &VarHeader) -> Vec<SampleValue> {
+        (0..var.count.max(1) as usize)
+            .filter_map(|index| self.get_by_header_at(var, index))
+            .collect()
+    }
 }
@@ -0,0 +1,481 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::json;
+use tungstenite::{
+    handshake::{server::NoCallback, MidHandshake},
+    protocol::WebSocketConfig,
+    HandshakeError, Message, ServerHandshake, WebSocket,
+};
+
+use crate::{error::Error, headers::VarHeader, samples::Sample, IbtReader};
+
+// Largest subscription message accepted over UDP.
+const MAX_DATAGRAM: usize = 65_507;
+// Clients that haven't finished the WebSocket handshake by then are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Frames queued for a WebSocket client that isn't reading them, past this
+// it's dropped rather than buffering without limit.
+const MAX_WRITE_BUFFER: usize = 1 << 20;
+
+type Handshake = MidHandshake<ServerHandshake<TcpStream, NoCallback>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    // {"tick":120,"values":[54.05,[1,2]]}, values in subscription order and
+    // arrays for variables with more than one element
+    #[default]
+    Json,
+    // little endian u32 tick followed by every value as an f64
+    Binary,
+}
+
+// Sent by a client (as a WebSocket text message or a UDP datagram) to choose
+// what it receives, e.g. {"channels":["Speed","RPM"],"rate":10}. Sending
+// another one replaces it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Subscription {
+    pub channels: Vec<String>,
+    // Hz, every sample when missing
+    pub rate: Option<f64>,
+    #[serde(default)]
+    pub format: Format,
+}
+
+enum Connection {
+    WebSocket(Box<WebSocket<TcpStream>>),
+    Udp(SocketAddr),
+}
+
+struct Client {
+    connection: Connection,
+    vars: Vec<VarHeader>,
+    every: u32,
+    format: Format,
+}
+
+// Pushes samples to dashboards on WebSocket and/or UDP clients, each one
+// only gets the channels it subscribed to at the rate it asked for.
+pub struct TelemetryServer {
+    vars: Vec<VarHeader>,
+    tick_rate: i32,
+    realtime: bool,
+    websocket: Option<TcpListener>,
+    udp: Option<UdpSocket>,
+    clients: Vec<Client>,
+    handshakes: Vec<(Handshake, Instant)>,
+    tick: u32,
+}
+
+impl TelemetryServer {
+    pub fn new(vars: Vec<VarHeader>, tick_rate: i32) -> TelemetryServer {
+        TelemetryServer {
+            vars,
+            tick_rate,
+            realtime: true,
+            websocket: None,
+            udp: None,
+            clients: vec![],
+            handshakes: vec![],
+            tick: 0,
+        }
+    }
+
+    pub fn for_reader(reader: &IbtReader) -> TelemetryServer {
+        TelemetryServer::new(reader.vars.clone(), reader.header.tick_rate)
+    }
+
+    pub fn websocket<A: ToSocketAddrs>(mut self, addr: A) -> Result<TelemetryServer, Error> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        self.websocket = Some(listener);
+        Ok(self)
+    }
+
+    pub fn udp<A: ToSocketAddrs>(mut self, addr: A) -> Result<TelemetryServer, Error> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        self.udp = Some(socket);
+        Ok(self)
+    }
+
+    // When enabled (the default) `serve` waits between samples to honour the
+    // tick rate, otherwise samples are pushed as fast as they're read.
+    pub fn realtime(mut self, realtime: bool) -> TelemetryServer {
+        self.realtime = realtime;
+        self
+    }
+
+    pub fn websocket_addr(&self) -> Option<SocketAddr> {
+        self.websocket.as_ref()?.local_addr().ok()
+    }
+
+    pub fn udp_addr(&self) -> Option<SocketAddr> {
+        self.udp.as_ref()?.local_addr().ok()
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn serve<I: IntoIterator<Item = Sample>>(&mut self, samples: I) -> Result<(), Error> {
+        let interval = Duration::from_secs_f64(1.0 / self.tick_rate.max(1) as f64);
+        let mut next = Instant::now();
+        for sample in samples {
+            self.push(&sample)?;
+            if self.realtime {
+                next += interval;
+                thread::sleep(next.saturating_duration_since(Instant::now()));
+            }
+        }
+        Ok(())
+    }
+
+    // Accepts new clients, reads their subscriptions and sends them this
+    // sample if it's due. Clients that went away are dropped.
+    pub fn push(&mut self, sample: &Sample) -> Result<(), Error> {
+        self.accept()?;
+        self.receive()?;
+
+        let tick = self.tick;
+        let udp = &self.udp;
+        self.clients.retain_mut(|client| {
+            if client.vars.is_empty() || !tick.is_multiple_of(client.every) {
+                return true;
+            }
+            let frame = encode(tick, sample, &client.vars, client.format);
+            match &mut client.connection {
+                Connection::WebSocket(socket) => send_websocket(socket, frame),
+                Connection::Udp(addr) => match udp {
+                    Some(udp) => send_udp(udp, *addr, frame.into_data()),
+                    None => false,
+                },
+            }
+        });
+        self.tick = self.tick.wrapping_add(1);
+        Ok(())
+    }
+
+    fn accept(&mut self) -> Result<(), Error> {
+        let listener = match &self.websocket {
+            Some(listener) => listener,
+            None => return Ok(()),
+        };
+        // Handshakes don't wait for the client either, the ones that can't
+        // finish yet are picked up again on the next push.
+        let mut handshakes = vec![];
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    let config = WebSocketConfig::default().max_write_buffer_size(MAX_WRITE_BUFFER);
+                    let handshake = tungstenite::accept_with_config(stream, Some(config));
+                    handshakes.push((handshake, Instant::now()));
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        for (handshake, started) in self.handshakes.drain(..) {
+            handshakes.push((handshake.handshake(), started));
+        }
+
+        for (result, started) in handshakes {
+            match result {
+                Ok(socket) => self.clients.push(Client {
+                    connection: Connection::WebSocket(Box::new(socket)),
+                    vars: vec![],
+                    every: 1,
+                    format: Format::Json,
+                }),
+                Err(HandshakeError::Interrupted(handshake))
+                    if started.elapsed() < HANDSHAKE_TIMEOUT =>
+                {
+                    self.handshakes.push((handshake, started))
+                }
+                Err(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(), Error> {
+        let mut messages: Vec<(Option<SocketAddr>, usize, Vec<u8>)> = vec![];
+        for (index, client) in self.clients.iter_mut().enumerate() {
+            if let Connection::WebSocket(socket) = &mut client.connection {
+                while let Ok(message) = socket.read() {
+                    if message.is_text() || message.is_binary() {
+                        messages.push((None, index, message.into_data().to_vec()));
+                    }
+                }
+            }
+        }
+        if let Some(udp) = &self.udp {
+            let mut buffer = vec![0; MAX_DATAGRAM];
+            loop {
+                match udp.recv_from(&mut buffer) {
+                    Ok((size, addr)) => messages.push((Some(addr), 0, buffer[..size].to_vec())),
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        }
+
+        for (addr, index, data) in messages {
+            let index = match addr {
+                Some(addr) => self.udp_client(addr),
+                None => index,
+            };
+            self.subscribe(index, &data);
+        }
+        Ok(())
+    }
+
+    fn udp_client(&mut self, addr: SocketAddr) -> usize {
+        let existing = self
+            .clients
+            .iter()
+            .position(|client| matches!(client.connection, Connection::Udp(a) if a == addr));
+        existing.unwrap_or_else(|| {
+            self.clients.push(Client {
+                connection: Connection::Udp(addr),
+                vars: vec![],
+                every: 1,
+                format: Format::Json,
+            });
+            self.clients.len() - 1
+        })
+    }
+
+    fn subscribe(&mut self, index: usize, data: &[u8]) {
+        let result = serde_json::from_slice::<Subscription>(data)
+            .map_err(|err| err.to_string())
+            .and_then(|subscription| {
+                let vars = subscription
+                    .channels
+                    .iter()
+                    .map(|name| {
+                        self.vars
+                            .iter()
+                            .find(|var| var.name == *name)
+                            .cloned()
+                            .ok_or(format!("unknown channel {name}"))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok((subscription, vars))
+            });
+
+        let tick_rate = self.tick_rate as f64;
+        let client = &mut self.clients[index];
+        let reply = match result {
+            Ok((subscription, vars)) => {
+                let units: Vec<&String> = vars.iter().map(|var| &var.unit).collect();
+                let reply = json!({ "channels": &subscription.channels, "units": units });
+                client.every = subscription
+                    .rate
+                    .map_or(1, |rate| (tick_rate / rate).round().max(1.0) as u32);
+                client.format = subscription.format;
+                client.vars = vars;
+                reply
+            }
+            Err(err) => json!({ "error": err }),
+        };
+
+        let reply = Message::text(reply.to_string());
+        match &mut client.connection {
+            Connection::WebSocket(socket) => {
+                send_websocket(socket, reply);
+            }
+            Connection::Udp(addr) => {
+                if let Some(udp) = &self.udp {
+                    send_udp(udp, *addr, reply.into_data());
+                }
+            }
+        }
+    }
+}
+
+fn encode(tick: u32, sample: &Sample, vars: &[VarHeader], format: Format) -> Message {
+    match format {
+        Format::Json => {
+            let values: Vec<serde_json::Value> = vars
+                .iter()
+                .map(|var| {
+                    let values: Vec<f64> = sample
+                        .get_all_by_header(var)
+                        .iter()
+                        .map(|value| value.as_f64())
+                        .collect();
+                    match values.as_slice() {
+                        [value] => json!(value),
+                        _ => json!(values),
+                    }
+                })
+                .collect();
+            Message::text(json!({ "tick": tick, "values": values }).to_string())
+        }
+        Format::Binary => {
+            let mut data = tick.to_le_bytes().to_vec();
+            for var in vars {
+                for value in sample.get_all_by_header(var) {
+                    data.extend_from_slice(&value.as_f64().to_le_bytes());
+                }
+            }
+            Message::binary(data)
+        }
+    }
+}
+
+// Returns false once the client is gone.
+fn send_websocket(socket: &mut WebSocket<TcpStream>, message: Message) -> bool {
+    match socket.send(message) {
+        Ok(()) => true,
+        // queued, it goes out with the next frame
+        Err(tungstenite::Error::Io(err)) => err.kind() == io::ErrorKind::WouldBlock,
+        // the client stopped reading, `MAX_WRITE_BUFFER` is queued already
+        Err(tungstenite::Error::WriteBufferFull(_)) => false,
+        Err(_) => false,
+    }
+}
+
+fn send_udp(socket: &UdpSocket, addr: SocketAddr, data: impl AsRef<[u8]>) -> bool {
+    match socket.send_to(data.as_ref(), addr) {
+        Ok(_) => true,
+        Err(err) => err.kind() == io::ErrorKind::WouldBlock,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let mut server = TelemetryServer::for_reader(&reader)
+            .websocket("127.0.0.1:0")
+            .unwrap()
+            .udp("127.0.0.1:0")
+            .unwrap()
+            .realtime(false);
+        let websocket_addr = server.websocket_addr().unwrap();
+        let udp_addr = server.udp_addr().unwrap();
+
+        let websocket = thread::spawn(move || {
+            let (mut socket, _) = tungstenite::connect(format!("ws://{websocket_addr}")).unwrap();
+            socket
+                .send(Message::text(r#"{"channels":["Speed","Gear"],"rate":30}"#))
+                .unwrap();
+            let reply: serde_json::Value =
+                serde_json::from_str(socket.read().unwrap().to_text().unwrap()).unwrap();
+            assert_eq!(reply["units"], json!(["m/s", ""]));
+            let first: serde_json::Value =
+                serde_json::from_str(socket.read().unwrap().to_text().unwrap()).unwrap();
+            let second: serde_json::Value =
+                serde_json::from_str(socket.read().unwrap().to_text().unwrap()).unwrap();
+            assert_eq!(first["values"].as_array().unwrap().len(), 2);
+            assert_eq!(
+                second["tick"].as_u64().unwrap() - first["tick"].as_u64().unwrap(),
+                2
+            );
+        });
+
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        udp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        udp.send_to(br#"{"channels":["Nope"]}"#, udp_addr).unwrap();
+        udp.send_to(br#"{"channels":["RPM"],"format":"binary"}"#, udp_addr)
+            .unwrap();
+
+        let samples: Vec<Sample> = reader.samples().take(600).collect();
+        for sample in samples.iter().cycle() {
+            if websocket.is_finished() {
+                break;
+            }
+            server.push(sample).unwrap();
+        }
+        websocket.join().unwrap();
+        assert_eq!(server.clients(), 2);
+
+        let mut buffer = [0; 1024];
+        let size = udp.recv(&mut buffer).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&buffer[..size]).unwrap(),
+            json!({"error": "unknown channel Nope"})
+        );
+        udp.recv(&mut buffer).unwrap();
+        assert_eq!(udp.recv(&mut buffer).unwrap(), 12);
+    }
+
+    #[test]
+    fn test_server_client_not_reading() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let mut server = TelemetryServer::for_reader(&reader)
+            .websocket("127.0.0.1:0")
+            .unwrap()
+            .realtime(false);
+        let websocket_addr = server.websocket_addr().unwrap();
+
+        // subscribes to every channel and never reads a frame
+        let channels: Vec<&str> = reader.vars.iter().map(|var| var.name.as_str()).collect();
+        let subscription = json!({ "channels": channels }).to_string();
+        let (done, wait) = std::sync::mpsc::channel::<()>();
+        let websocket = thread::spawn(move || {
+            let (mut socket, _) = tungstenite::connect(format!("ws://{websocket_addr}")).unwrap();
+            socket.send(Message::text(subscription)).unwrap();
+            wait.recv().unwrap();
+        });
+
+        let samples: Vec<Sample> = reader.samples().take(60).collect();
+        let mut samples = samples.iter().cycle();
+        while server.clients() == 0 {
+            server.push(samples.next().unwrap()).unwrap();
+        }
+        for sample in samples.take(100_000) {
+            server.push(sample).unwrap();
+            if server.clients() == 0 {
+                break;
+            }
+        }
+        assert_eq!(server.clients(), 0);
+        done.send(()).unwrap();
+        websocket.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_silent_client() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let mut server = TelemetryServer::for_reader(&reader)
+            .websocket("127.0.0.1:0")
+            .unwrap()
+            .realtime(false);
+        let websocket_addr = server.websocket_addr().unwrap();
+
+        // connects but never sends the handshake
+        let _silent = TcpStream::connect(websocket_addr).unwrap();
+        let sample = reader.samples().next().unwrap();
+        let started = Instant::now();
+        for _ in 0..10 {
+            server.push(&sample).unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(started.elapsed() < HANDSHAKE_TIMEOUT);
+        assert_eq!(server.clients(), 0);
+
+        // others still get through while it waits
+        let websocket = thread::spawn(move || {
+            tungstenite::connect(format!("ws://{websocket_addr}")).unwrap();
+        });
+        while !websocket.is_finished() {
+            server.push(&sample).unwrap();
+        }
+        websocket.join().unwrap();
+        assert_eq!(server.clients(), 1);
+    }
+}
@@ -0,0 +1,248 @@
+use serde_yaml::Value;
+
+// A single difference between two versions of the session info, `path`
+// points at the value like `SessionInfo.Sessions[0].ResultsPositions[CarIdx=2].Lap`.
+// Lists of cars (drivers, results) are matched by `CarIdx` rather than by
+// position, so a car moving up the order doesn't show up as every entry
+// changing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionInfoDiff {
+    // `sesion_info_update` of the new version
+    pub update: i32,
+    pub changes: Vec<Change>,
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } => path,
+            Change::Removed { path, .. } => path,
+            Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl SessionInfoDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    // Changes at or below `path`, e.g. "DriverInfo.Drivers" for drivers
+    // joining, leaving or swapping.
+    pub fn under<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a Change> + 'a {
+        self.changes.iter().filter(move |change| {
+            change
+                .path()
+                .strip_prefix(path)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+        })
+    }
+}
+
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_at(String::new(), old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: String, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Mapping(old), Value::Mapping(new)) => {
+            for (key, old_value) in old {
+                let path = join(&path, key);
+                match new.get(key) {
+                    Some(new_value) => diff_at(path, old_value, new_value, changes),
+                    None => changes.push(Change::Removed {
+                        path,
+                        value: old_value.clone(),
+                    }),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    changes.push(Change::Added {
+                        path: join(&path, key),
+                        value: new_value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Sequence(old), Value::Sequence(new)) if by_car(old) && by_car(new) => {
+            for old_item in old {
+                let car_idx = &old_item["CarIdx"];
+                let path = format!("{path}[CarIdx={}]", key_text(car_idx));
+                match new.iter().find(|new_item| new_item["CarIdx"] == *car_idx) {
+                    Some(new_item) => diff_at(path, old_item, new_item, changes),
+                    None => changes.push(Change::Removed {
+                        path,
+                        value: old_item.clone(),
+                    }),
+                }
+            }
+            for new_item in new {
+                let car_idx = &new_item["CarIdx"];
+                if !old.iter().any(|old_item| old_item["CarIdx"] == *car_idx) {
+                    changes.push(Change::Added {
+                        path: format!("{path}[CarIdx={}]", key_text(car_idx)),
+                        value: new_item.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Sequence(old), Value::Sequence(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                let path = format!("{path}[{index}]");
+                match (old.get(index), new.get(index)) {
+                    (Some(old), Some(new)) => diff_at(path, old, new, changes),
+                    (Some(old), None) => changes.push(Change::Removed {
+                        path,
+                        value: old.clone(),
+                    }),
+                    (None, Some(new)) => changes.push(Change::Added {
+                        path,
+                        value: new.clone(),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        (old, new) if old != new => changes.push(Change::Changed {
+            path,
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+// Every entry has its own `CarIdx`, unlike e.g. radio frequencies that all
+// use -1.
+fn by_car(items: &[Value]) -> bool {
+    items.iter().enumerate().all(|(index, item)| {
+        item.get("CarIdx").is_some_and(|car_idx| {
+            !car_idx.is_null()
+                && !items[..index]
+                    .iter()
+                    .any(|other| other["CarIdx"] == *car_idx)
+        })
+    })
+}
+
+fn key_text(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        key => serde_yaml::to_string(key)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+fn join(path: &str, key: &Value) -> String {
+    let key = key_text(key);
+    if path.is_empty() {
+        key
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old: Value = serde_yaml::from_str(
+            "
+SessionInfo:
+ Sessions:
+ - SessionNum: 0
+   ResultsPositions:
+ - SessionNum: 1
+   ResultsPositions:
+   - CarIdx: 3
+     Position: 1
+   - CarIdx: 5
+     Position: 2
+DriverInfo:
+ Drivers:
+ - CarIdx: 0
+   UserName: Georgi Martsenkov
+",
+        )
+        .unwrap();
+        let new: Value = serde_yaml::from_str(
+            "
+SessionInfo:
+ Sessions:
+ - SessionNum: 0
+   ResultsPositions:
+   - Position: 1
+ - SessionNum: 1
+   ResultsPositions:
+   - CarIdx: 5
+     Position: 1
+   - CarIdx: 3
+     Position: 2
+DriverInfo:
+ Drivers:
+ - CarIdx: 1
+   UserName: Georgi Martsenkov
+ - CarIdx: 0
+   UserName: Someone Else
+",
+        )
+        .unwrap();
+
+        let diff = SessionInfoDiff {
+            update: 1,
+            changes: diff(&old, &new),
+        };
+        assert_eq!(diff.changes.len(), 5);
+        assert!(matches!(
+            &diff.changes[0],
+            Change::Changed { path, old: Value::Null, .. } if path == "SessionInfo.Sessions[0].ResultsPositions"
+        ));
+        // the two cars swapped places
+        let positions: Vec<&str> = diff
+            .under("SessionInfo.Sessions[1].ResultsPositions")
+            .map(Change::path)
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                "SessionInfo.Sessions[1].ResultsPositions[CarIdx=3].Position",
+                "SessionInfo.Sessions[1].ResultsPositions[CarIdx=5].Position"
+            ]
+        );
+        // car 0 moved down the list but only its driver changed
+        let drivers: Vec<&str> = diff.under("DriverInfo.Drivers").map(Change::path).collect();
+        assert_eq!(
+            drivers,
+            vec![
+                "DriverInfo.Drivers[CarIdx=0].UserName",
+                "DriverInfo.Drivers[CarIdx=1]"
+            ]
+        );
+        assert_eq!(diff.under("DriverInfo.Driver").count(), 0);
+        assert!(super::diff(&old, &old).is_empty());
+    }
+}
@@ -63,6 +63,25 @@ pub struct WeekendInfo {
     pub weekend_options: WeekendOptions,
 }
 
+impl WeekendInfo {
+    pub fn north_offset(&self) -> Option<f64> {
+        parse_value(&self.track_north_offset)
+    }
+
+    pub fn latitude(&self) -> Option<f64> {
+        parse_value(&self.track_latitude)
+    }
+
+    pub fn longitude(&self) -> Option<f64> {
+        parse_value(&self.track_longitude)
+    }
+}
+
+// Most numbers in the session info come with their unit, e.g. "1.5876 rad"
+fn parse_value(value: &str) -> Option<f64> {
+    value.split_whitespace().next()?.parse().ok()
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct WeekendOptions {
@@ -285,7 +304,7 @@ pub struct DriverInfo {
     pub drivers: Vec<Driver>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct Driver {
     pub car_idx: i32,
@@ -400,7 +419,7 @@ pub struct Sessions {
     pub sessions: Vec<Session>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct Session {
     pub session_num: i32,
@@ -414,6 +433,8 @@ pub struct Session {
     pub session_skipped: i32,
     pub session_run_groups_used: i32,
     pub session_enforce_tire_compound_change: i32,
+    // empty (null) until the first lap is completed
+    pub results_positions: Option<Vec<ResultPosition>>,
     pub results_fastest_lap: Vec<FastestLap>,
     pub results_average_lap_time: f32,
     pub results_num_caution_flags: i32,
@@ -423,7 +444,28 @@ pub struct Session {
     pub results_official: i32,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ResultPosition {
+    pub position: i32,
+    // zero based, unlike `position`
+    pub class_position: i32,
+    pub car_idx: i32,
+    pub lap: i32,
+    pub time: f32,
+    pub fastest_lap: i32,
+    pub fastest_time: f32,
+    pub last_time: f32,
+    pub laps_led: i32,
+    pub laps_complete: i32,
+    pub joker_laps_complete: Option<i32>,
+    pub laps_driven: f32,
+    pub incidents: i32,
+    pub reason_out_id: i32,
+    pub reason_out_str: String,
+}
+
+#[derive(Deserialize, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct FastestLap {
     #[serde(rename = "CarIdx")]
@@ -0,0 +1,181 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::{
+    error::Error,
+    headers::{
+        DiskHeader, Header, VarHeader, DISK_HEADER_BYTES_SIZE, HEADER_BYTES_SIZE,
+        VAR_HEADER_BYTES_SIZE,
+    },
+    parse_session_info, parse_vars,
+    samples::Sample,
+    session_info::SessionInfo,
+};
+
+// Reads an .ibt file in a single forward pass, for sources that can't seek
+// such as pipes, HTTP bodies or decompressors.
+pub struct IbtStreamReader<R> {
+    reader: R,
+    pub header: Header,
+    pub disk_header: DiskHeader,
+    pub vars: Vec<VarHeader>,
+    pub session_info: SessionInfo,
+}
+
+impl IbtStreamReader<Box<dyn Read>> {
+    // Opens a file, .ibt.gz and .ibt.zst files are decompressed on the fly
+    // when the `compression` feature is enabled.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<IbtStreamReader<Box<dyn Read>>, Error> {
+        let file = io::BufReader::new(File::open(path)?);
+        #[cfg(feature = "compression")]
+        let file = decompress(file)?;
+        IbtStreamReader::new(Box::new(file))
+    }
+}
+
+impl<R: Read> IbtStreamReader<R> {
+    pub fn new(reader: R) -> Result<IbtStreamReader<R>, Error> {
+        let mut stream = StreamPosition {
+            reader,
+            position: 0,
+        };
+        let header = Header::from(stream.read_at(0, HEADER_BYTES_SIZE)?);
+        // the length of a stream isn't known up front
+        header.validate(u64::MAX)?;
+        let disk_header =
+            DiskHeader::from(stream.read_at(HEADER_BYTES_SIZE, DISK_HEADER_BYTES_SIZE)?);
+
+        let var_headers = (
+            header.var_header_offset as usize,
+            header.num_vars as usize * VAR_HEADER_BYTES_SIZE,
+        );
+        let session_info = (
+            header.sesion_info_offset as usize,
+            header.sesion_info_length as usize,
+        );
+        let (vars_data, session_info_data) = if var_headers.0 <= session_info.0 {
+            let vars = stream.read_at(var_headers.0, var_headers.1)?;
+            (vars, stream.read_at(session_info.0, session_info.1)?)
+        } else {
+            let session_info = stream.read_at(session_info.0, session_info.1)?;
+            (stream.read_at(var_headers.0, var_headers.1)?, session_info)
+        };
+        let vars = parse_vars(&vars_data, header.num_vars);
+        for var in &vars {
+            var.validate(header.buf_len)?;
+        }
+        stream.skip_to(header.buf_offset as usize)?;
+
+        Ok(IbtStreamReader {
+            reader: stream.reader,
+            vars,
+            session_info: parse_session_info(&session_info_data)?,
+            header,
+            disk_header,
+        })
+    }
+
+    pub fn samples(&mut self) -> impl Iterator<Item = Sample> + '_ {
+        let length = self.header.buf_len as usize;
+        std::iter::from_fn(move || {
+            let mut data = vec![0; length];
+            match self.reader.read_exact(&mut data) {
+                Ok(_) => Some(Sample::from(data)),
+                Err(_) => None,
+            }
+        })
+    }
+
+    pub fn find_var(&self, name: String) -> Option<VarHeader> {
+        self.vars.iter().find(|var| var.name == name).cloned()
+    }
+}
+
+struct StreamPosition<R> {
+    reader: R,
+    position: usize,
+}
+
+impl<R: Read> StreamPosition<R> {
+    fn skip_to(&mut self, offset: usize) -> Result<(), Error> {
+        if offset < self.position {
+            return Err(Error::UnorderedLayout);
+        }
+        let skip = (offset - self.position) as u64;
+        let skipped = io::copy(&mut (&mut self.reader).take(skip), &mut io::sink())?;
+        if skipped < skip {
+            return Err(Error::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+        self.position = offset;
+        Ok(())
+    }
+
+    fn read_at(&mut self, offset: usize, size: usize) -> Result<Vec<u8>, Error> {
+        self.skip_to(offset)?;
+        let mut buffer = vec![0; size];
+        self.reader.read_exact(&mut buffer)?;
+        self.position += size;
+        Ok(buffer)
+    }
+}
+
+// Picks the decompressor by looking at the magic bytes so it also works for
+// streams that don't have a file name.
+#[cfg(feature = "compression")]
+pub fn decompress<R: io::BufRead + 'static>(mut reader: R) -> Result<Box<dyn Read>, Error> {
+    const GZIP: [u8; 2] = [0x1f, 0x8b];
+    const ZSTD: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+    let magic = reader.fill_buf()?;
+    if magic.starts_with(&GZIP) {
+        Ok(Box::new(flate2::bufread::GzDecoder::new(reader)))
+    } else if magic.starts_with(&ZSTD) {
+        Ok(Box::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::SampleValue;
+
+    #[test]
+    fn test_stream_reader() {
+        let file = File::open("./test/fixtures/amg.ibt").unwrap();
+        let mut reader = IbtStreamReader::new(file).unwrap();
+        assert_eq!(reader.header.tick_rate, 60);
+        assert_eq!(reader.vars.len(), 267);
+        assert_eq!(reader.disk_header.record_count, 3371);
+
+        let rpm = reader.find_var("RPM".to_string()).unwrap();
+        let samples: Vec<Sample> = reader.samples().collect();
+        assert_eq!(samples.len(), 3371);
+        assert_eq!(
+            samples[1001].get_by_header(&rpm).unwrap(),
+            SampleValue::Float32(991.8974)
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_stream_reader() {
+        use std::io::Write;
+
+        let data = std::fs::read("./test/fixtures/amg.ibt").unwrap();
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        gzip.write_all(&data).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::encode_all(&data[..], 1).unwrap();
+
+        for compressed in [gzip, zstd] {
+            let input = decompress(io::Cursor::new(compressed)).unwrap();
+            let mut reader = IbtStreamReader::new(input).unwrap();
+            assert_eq!(reader.samples().count(), 3371);
+        }
+    }
+}
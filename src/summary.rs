@@ -0,0 +1,221 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use yore::code_pages::CP1252;
+
+use crate::{
+    error::Error,
+    headers::{DiskHeader, Header, DISK_HEADER_BYTES_SIZE, HEADER_BYTES_SIZE},
+    read_bytes_file,
+};
+
+// Only these top level sections of the session info are looked at.
+const SECTIONS: [&[u8]; 3] = [b"WeekendInfo", b"SessionInfo", b"DriverInfo"];
+
+// The handful of things needed to catalogue a file, read without touching
+// the var headers, the samples or most of the session info.
+pub struct IbtSummary {
+    pub header: Header,
+    pub disk_header: DiskHeader,
+    pub num_vars: i32,
+    pub track_name: String,
+    pub track_display_name: String,
+    pub track_config_name: String,
+    pub series_id: i32,
+    pub session_id: i32,
+    pub sub_session_id: i32,
+    pub event_type: String,
+    pub session_types: Vec<String>,
+    pub car: Option<String>,
+    pub driver: Option<String>,
+    pub date: String,
+    // seconds
+    pub duration: f64,
+}
+
+// What the summary needs from the session info.
+#[derive(Default)]
+struct PartialSessionInfo {
+    track_name: String,
+    track_display_name: String,
+    track_config_name: String,
+    series_id: i32,
+    session_id: i32,
+    sub_session_id: i32,
+    event_type: String,
+    date: String,
+    session_types: Vec<String>,
+    driver_car_idx: i32,
+    drivers: Vec<PartialDriver>,
+}
+
+struct PartialDriver {
+    car_idx: i32,
+    user_name: String,
+    car_screen_name: String,
+}
+
+impl IbtSummary {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<IbtSummary, Error> {
+        IbtSummary::from_reader(&mut File::open(path)?)
+    }
+
+    pub fn from_reader<R: Read + Seek>(file: &mut R) -> Result<IbtSummary, Error> {
+        let file_len = file.seek(SeekFrom::End(0))?;
+        let header = Header::from(read_bytes_file(file, 0, HEADER_BYTES_SIZE)?);
+        header.validate(file_len)?;
+        let disk_header = DiskHeader::from(read_bytes_file(
+            file,
+            HEADER_BYTES_SIZE,
+            DISK_HEADER_BYTES_SIZE,
+        )?);
+        let session_info_data = read_bytes_file(
+            file,
+            header.sesion_info_offset as usize,
+            header.sesion_info_length as usize,
+        )?;
+        let info = scan_session_info(&session_info_data)?;
+
+        let driver = info
+            .drivers
+            .iter()
+            .find(|driver| driver.car_idx == info.driver_car_idx);
+
+        Ok(IbtSummary {
+            num_vars: header.num_vars,
+            track_name: info.track_name,
+            track_display_name: info.track_display_name,
+            track_config_name: info.track_config_name,
+            series_id: info.series_id,
+            session_id: info.session_id,
+            sub_session_id: info.sub_session_id,
+            event_type: info.event_type,
+            session_types: info.session_types,
+            car: driver.map(|driver| driver.car_screen_name.clone()),
+            driver: driver.map(|driver| driver.user_name.clone()),
+            date: info.date,
+            duration: disk_header.record_count as f64 / header.tick_rate as f64,
+            header,
+            disk_header,
+        })
+    }
+}
+
+// Picks the values out line by line rather than parsing the YAML, which is
+// most of the time it takes to open a file (the driver list alone is
+// thousands of lines in a full race). Only values are decoded and the scan
+// stops once the three sections have been seen.
+fn scan_session_info(data: &[u8]) -> Result<PartialSessionInfo, Error> {
+    let mut info = PartialSessionInfo::default();
+    let mut seen = 0;
+    let mut section: &[u8] = b"";
+    let mut parent: &[u8] = b"";
+    for line in data.split(|byte| *byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let indent = line.iter().take_while(|byte| **byte == b' ').count();
+        let (item, rest) = match line[indent..].strip_prefix(b"- ") {
+            Some(rest) => (true, rest),
+            None => (false, &line[indent..]),
+        };
+        let colon = match rest.iter().position(|byte| *byte == b':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let (key, value) = (&rest[..colon], &rest[colon + 1..]);
+
+        if indent == 0 {
+            if SECTIONS.contains(&section) {
+                seen += 1;
+            }
+            if seen == SECTIONS.len() {
+                break;
+            }
+            section = key;
+            continue;
+        }
+        if indent == 1 && !item {
+            parent = key;
+        }
+        match (section, key, indent) {
+            (b"WeekendInfo", b"TrackName", 1) => info.track_name = text(value)?,
+            (b"WeekendInfo", b"TrackDisplayName", 1) => info.track_display_name = text(value)?,
+            (b"WeekendInfo", b"TrackConfigName", 1) => info.track_config_name = text(value)?,
+            (b"WeekendInfo", b"SeriesID", 1) => info.series_id = int(value)?,
+            (b"WeekendInfo", b"SessionID", 1) => info.session_id = int(value)?,
+            (b"WeekendInfo", b"SubSessionID", 1) => info.sub_session_id = int(value)?,
+            (b"WeekendInfo", b"EventType", 1) => info.event_type = text(value)?,
+            (b"WeekendInfo", b"Date", 2) if parent == b"WeekendOptions" => info.date = text(value)?,
+            (b"SessionInfo", b"SessionType", 3) => info.session_types.push(text(value)?),
+            (b"DriverInfo", b"DriverCarIdx", 1) => info.driver_car_idx = int(value)?,
+            (b"DriverInfo", b"CarIdx", 1) if item => info.drivers.push(PartialDriver {
+                car_idx: int(value)?,
+                user_name: String::new(),
+                car_screen_name: String::new(),
+            }),
+            (b"DriverInfo", b"UserName", 3) => {
+                if let Some(driver) = info.drivers.last_mut() {
+                    driver.user_name = text(value)?;
+                }
+            }
+            (b"DriverInfo", b"CarScreenName", 3) => {
+                if let Some(driver) = info.drivers.last_mut() {
+                    driver.car_screen_name = text(value)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    if SECTIONS.contains(&section) {
+        seen += 1;
+    }
+    if seen < SECTIONS.len() {
+        return Err(Error::SessionInfo(serde::de::Error::custom(
+            "missing WeekendInfo, SessionInfo or DriverInfo",
+        )));
+    }
+    Ok(info)
+}
+
+// iRacing writes plain scalars, anything quoted goes through the YAML parser.
+fn text(value: &[u8]) -> Result<String, Error> {
+    let value = CP1252.decode(value);
+    let value = value.trim();
+    if value.starts_with(['"', '\'']) {
+        return Ok(serde_yaml::from_str(value)?);
+    }
+    Ok(value.to_string())
+}
+
+fn int(value: &[u8]) -> Result<i32, Error> {
+    Ok(serde_yaml::from_str(&text(value)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let summary = IbtSummary::read("./test/fixtures/amg.ibt").unwrap();
+        assert_eq!(summary.num_vars, 267);
+        assert_eq!(summary.track_name, "spielberg gp");
+        assert_eq!(summary.track_config_name, "Grand Prix");
+        assert_eq!(summary.event_type, "Test");
+        assert_eq!(summary.session_types, vec!["Offline Testing"]);
+        assert_eq!(summary.car.as_deref(), Some("Mercedes AMG GT4"));
+        assert_eq!(summary.driver.as_deref(), Some("Georgi Martsenkov"));
+        assert_eq!(summary.date, "2023-04-15");
+        assert_eq!(summary.disk_header.start_date, 1681113045);
+        assert_eq!(summary.duration, 3371.0 / 60.0);
+
+        let mut data = std::fs::read("./test/fixtures/amg.ibt").unwrap();
+        data[16..20].copy_from_slice(&(-1_i32).to_le_bytes());
+        assert!(matches!(
+            IbtSummary::from_reader(&mut std::io::Cursor::new(data)),
+            Err(Error::InvalidHeader(_))
+        ));
+    }
+}
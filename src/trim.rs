@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
+
+use crate::{
+    error::Error, headers::DiskHeader, read_bytes_file, samples::Sample, writer::write_ibt,
+    IbtReader,
+};
+
+// Laps and session times start over in every session, so both are given
+// for a `SessionNum`.
+pub enum TrimRange {
+    // Every sample where `Lap` is this lap.
+    Lap { session_num: i32, lap: i32 },
+    // Samples with a `SessionTime` in the range, end excluded.
+    SessionTime { session_num: i32, range: Range<f64> },
+}
+
+impl TrimRange {
+    fn contains(&self, session_num: i32, lap: i32, session_time: f64) -> bool {
+        match self {
+            TrimRange::Lap {
+                session_num: range_session,
+                lap: range_lap,
+            } => session_num == *range_session && lap == *range_lap,
+            TrimRange::SessionTime {
+                session_num: range_session,
+                range,
+            } => session_num == *range_session && range.contains(&session_time),
+        }
+    }
+}
+
+pub fn trim_file<P: AsRef<Path>>(
+    reader: &mut IbtReader,
+    range: TrimRange,
+    path: P,
+) -> Result<DiskHeader, Error> {
+    trim(reader, range, BufWriter::new(File::create(path)?))
+}
+
+// Writes a copy of the file with only the samples in `range`. The session
+// info is kept as is, the disk header is updated for the samples that are
+// left and returned. `start_date` moves forward by the time that was cut
+// from the start, rounded to the whole seconds it's stored in, so times of
+// day worked out from it (e.g. GPS timestamps) can be up to half a second off.
+pub fn trim<W: Write>(
+    reader: &mut IbtReader,
+    range: TrimRange,
+    mut out: W,
+) -> Result<DiskHeader, Error> {
+    let session_num = reader
+        .find_var("SessionNum".to_string())
+        .ok_or_else(|| Error::MissingVar("SessionNum".to_string()))?;
+    let lap = reader
+        .find_var("Lap".to_string())
+        .ok_or_else(|| Error::MissingVar("Lap".to_string()))?;
+    let session_time = reader
+        .find_var("SessionTime".to_string())
+        .ok_or_else(|| Error::MissingVar("SessionTime".to_string()))?;
+    let read = |sample: &Sample| {
+        (
+            sample.get_by_header(&session_num).unwrap().int(),
+            sample.get_by_header(&lap).unwrap().int(),
+            sample.get_by_header(&session_time).unwrap().float_64(),
+        )
+    };
+
+    // The disk header comes before the samples, so they're gone through once
+    // to count them and again to write them.
+    let mut laps = BTreeSet::new();
+    let mut times: Option<(f64, f64)> = None;
+    let mut record_count = 0;
+    for sample in reader.samples() {
+        let (session, lap, time) = read(&sample);
+        if !range.contains(session, lap, time) {
+            continue;
+        }
+        laps.insert(lap);
+        times = Some(times.map_or((time, time), |(start, _)| (start, time)));
+        record_count += 1;
+    }
+    let (start_time, end_time) = times.ok_or(Error::NoSamples)?;
+    let elapsed = (start_time - reader.disk_header.start_time).round() as i64;
+    let disk_header = DiskHeader {
+        start_date: reader.disk_header.start_date + elapsed,
+        start_time,
+        end_time,
+        lap_count: laps.len() as i32,
+        record_count,
+    };
+
+    let session_info = read_bytes_file(
+        &mut reader.file,
+        reader.header.sesion_info_offset as usize,
+        reader.header.sesion_info_length as usize,
+    )?;
+    let header = reader.header.clone();
+    let vars = reader.vars.clone();
+    let samples = reader.samples().filter(|sample| {
+        let (session, lap, time) = read(sample);
+        range.contains(session, lap, time)
+    });
+    write_ibt(
+        &mut out,
+        &header,
+        &disk_header,
+        &vars,
+        &session_info,
+        samples,
+    )?;
+    Ok(disk_header)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::export::gps::GpsTrack;
+
+    #[test]
+    fn test_trim() {
+        let mut reader = IbtReader::open("./test/fixtures/amg.ibt").unwrap();
+        let mut data = vec![];
+        let range = TrimRange::SessionTime {
+            session_num: 0,
+            range: 60.0..70.0,
+        };
+        let disk_header = trim(&mut reader, range, &mut data).unwrap();
+        assert_eq!(disk_header.record_count, 598);
+        assert_eq!(disk_header.lap_count, 1);
+        // 54.05 to 60.0x is rounded to 6 seconds
+        assert_eq!(disk_header.start_date, reader.disk_header.start_date + 6);
+        assert!(disk_header.start_time >= 60.0 && disk_header.start_time < 60.02);
+        assert!(disk_header.end_time < 70.0 && disk_header.end_time > 69.98);
+
+        let mut trimmed = IbtReader::try_new(Box::new(Cursor::new(data))).unwrap();
+        assert_eq!(trimmed.disk_header.record_count, 598);
+        assert_eq!(trimmed.disk_header.start_time, disk_header.start_time);
+        assert_eq!(trimmed.session_info.weekend_info.track_name, "spielberg gp");
+        let session_time = trimmed.find_var("SessionTime".to_string()).unwrap();
+        let rpm = trimmed.find_var("RPM".to_string()).unwrap();
+        let samples: Vec<Sample> = trimmed.samples().collect();
+        assert_eq!(samples.len(), 598);
+        let first_time = samples[0].get_by_header(&session_time).unwrap().float_64();
+        assert_eq!(first_time, disk_header.start_time);
+        let original = reader
+            .samples()
+            .find(|sample| sample.get_by_header(&session_time).unwrap().float_64() == first_time)
+            .unwrap();
+        assert_eq!(samples[0].get_by_header(&rpm), original.get_by_header(&rpm));
+
+        // samples keep their time of day, give or take the rounding
+        let session = reader.sessions().remove(0);
+        let gps = GpsTrack::from_reader(&mut reader, &session).unwrap();
+        let session = trimmed.sessions().remove(0);
+        let trimmed_gps = GpsTrack::from_reader(&mut trimmed, &session).unwrap();
+        let epoch = |gps: &GpsTrack| gps.start_date as f64 - gps.session_start_time;
+        assert!((epoch(&trimmed_gps) - epoch(&gps)).abs() <= 0.5);
+
+        // the whole fixture is lap 0 of session 0
+        let mut data = vec![];
+        let lap = |session_num, lap| TrimRange::Lap { session_num, lap };
+        let disk_header = trim(&mut reader, lap(0, 0), &mut data).unwrap();
+        assert_eq!(disk_header.record_count, 3371);
+        assert!(matches!(
+            trim(&mut reader, lap(0, 1), vec![]),
+            Err(Error::NoSamples)
+        ));
+        assert!(matches!(
+            trim(&mut reader, lap(1, 0), vec![]),
+            Err(Error::NoSamples)
+        ));
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::Write;
+
+use crate::{
+    error::Error,
+    headers::{
+        DiskHeader, Header, VarBuf, VarHeader, DISK_HEADER_BYTES_SIZE, HEADER_BYTES_SIZE,
+        VAR_HEADER_BYTES_SIZE,
+    },
+    samples::Sample,
+};
+
+// Writes a complete .ibt file laid out the way iRacing does: header, disk
+// header, var headers, session info and then the samples. Offsets and
+// lengths in `header` are worked out again, everything else is kept.
+pub(crate) fn write_ibt<W: Write, I: IntoIterator<Item = Sample>>(
+    out: &mut W,
+    header: &Header,
+    disk_header: &DiskHeader,
+    vars: &[VarHeader],
+    session_info: &[u8],
+    samples: I,
+) -> Result<(), Error> {
+    let var_header_offset = HEADER_BYTES_SIZE + DISK_HEADER_BYTES_SIZE;
+    let session_info_offset = var_header_offset + vars.len() * VAR_HEADER_BYTES_SIZE;
+    let buf_offset = session_info_offset + session_info.len();
+
+    let header = Header {
+        sesion_info_length: session_info.len() as i32,
+        sesion_info_offset: session_info_offset as i32,
+        num_vars: vars.len() as i32,
+        var_header_offset: var_header_offset as i32,
+        num_buf: 1,
+        buf_offset: buf_offset as i32,
+        var_bufs: vec![VarBuf {
+            tick_count: header.var_bufs.first().map_or(0, |buf| buf.tick_count),
+            buf_offset: buf_offset as i32,
+        }],
+        ..header.clone()
+    };
+
+    out.write_all(&header.to_bytes())?;
+    out.write_all(&disk_header.to_bytes())?;
+    for var in vars {
+        out.write_all(&var.to_bytes())?;
+    }
+    out.write_all(session_info)?;
+    for sample in samples {
+        out.write_all(sample.as_bytes())?;
+    }
+    out.flush()?;
+    Ok(())
+}